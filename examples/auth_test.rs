@@ -1,13 +1,12 @@
 use merco_llmproxy::config::{LlmConfig, Provider};
-use merco_llmproxy::traits::{ChatMessage, CompletionRequest, LlmProvider, ChatMessageRole};
+use merco_llmproxy::traits::{ChatMessage, CompletionRequest};
 use std::env;
 use std::error::Error;
 
 #[tokio::main]
 async fn main() {
     // Load API key from environment variable
-    let api_key = env::var("OPENROUTER_API_KEY")
-        .expect("API key is required.");
+    let api_key = env::var("OPENROUTER_API_KEY").expect("API key is required.");
 
     // Configure for OpenRouter (using OpenAI provider)
     let config = LlmConfig::new(Provider::OpenAI)
@@ -22,15 +21,10 @@ async fn main() {
     // Create a simple request
     let request = CompletionRequest {
         model: "openai/gpt-3.5-turbo".to_string(), // Added model field
-        messages: vec![ChatMessage {
-            role: ChatMessageRole::User, // Use imported ChatMessageRole
-            content: Some("Say hello!".to_string()), // Fixed content type to Option<String>
-            tool_calls: None,
-            tool_call_id: None,
-        }],
+        messages: vec![ChatMessage::user("Say hello!".to_string())],
         temperature: Some(0.7),
         max_tokens: Some(50),
-        tools: None,
+        ..Default::default()
     };
 
     println!("Sending request: {:?}", request);
@@ -44,19 +38,19 @@ async fn main() {
             eprintln!("Error during API call: {}", e);
             // Attempt to print underlying reqwest error if available
             if let Some(source) = e.source() {
-                 if let Some(reqwest_err) = source.downcast_ref::<reqwest::Error>() {
-                     eprintln!("Underlying Reqwest Error: {:?}", reqwest_err);
-                     eprintln!("Is status error: {}", reqwest_err.is_status());
-                     if let Some(status) = reqwest_err.status() {
-                         eprintln!("Status code: {}", status);
-                     }
-                     if let Some(url) = reqwest_err.url() {
+                if let Some(reqwest_err) = source.downcast_ref::<reqwest::Error>() {
+                    eprintln!("Underlying Reqwest Error: {:?}", reqwest_err);
+                    eprintln!("Is status error: {}", reqwest_err.is_status());
+                    if let Some(status) = reqwest_err.status() {
+                        eprintln!("Status code: {}", status);
+                    }
+                    if let Some(url) = reqwest_err.url() {
                         eprintln!("URL: {}", url);
-                     }
-                 } else {
-                     eprintln!("Underlying error source: {:?}", source);
-                 }
-             }
+                    }
+                } else {
+                    eprintln!("Underlying error source: {:?}", source);
+                }
+            }
         }
     }
-} 
\ No newline at end of file
+}
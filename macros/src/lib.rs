@@ -3,11 +3,11 @@ extern crate proc_macro;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use quote::{quote, ToTokens};
+use syn::parse::Parse;
 use syn::{
-    parse_macro_input, Ident, ItemFn, Pat, PatType, FnArg, Meta, Lit, Expr,
-    punctuated::Punctuated, Token,
+    parse_macro_input, punctuated::Punctuated, Expr, FnArg, Ident, ItemFn, Lit, Meta, Pat, PatType,
+    Token,
 };
-use syn::parse::Parse;
 
 // Custom parsing for attribute arguments
 struct AttributeArgs {
@@ -26,7 +26,7 @@ impl Parse for AttributeArgs {
 ///
 /// # Example
 ///
-/// ```no_run
+/// ```ignore
 /// use merco_llmproxy::merco_tool;
 ///
 /// #[merco_tool(description = "Calculates the sum of two integers")]
@@ -81,13 +81,14 @@ pub fn merco_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
     // Generate parameter properties for JsonSchema
     let param_properties = fn_args.iter().map(|(name, type_str)| {
         let type_json = match type_str.as_str().trim() {
-            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => "integer",
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize" => "integer",
             "f32" | "f64" => "number",
             "String" | "& str" | "&'static str" => "string",
             "bool" => "boolean",
             _ => "object",
         };
-        
+
         quote! {
             props.insert(#name.to_string(), ::serde_json::json!({ "type": #type_json }));
         }
@@ -102,11 +103,15 @@ pub fn merco_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     // Generate function wrapper fields
     let fn_ident = &input_fn.sig.ident;
-    let arg_names: Vec<_> = fn_args.iter().map(|(name, _)| Ident::new(name, Span::call_site())).collect();
+    let arg_names: Vec<_> = fn_args
+        .iter()
+        .map(|(name, _)| Ident::new(name, Span::call_site()))
+        .collect();
     let arg_structs = fn_args.iter().map(|(name, ty_str)| {
         let name_ident = Ident::new(name, Span::call_site());
         // Parse the type string back into a Type syn object for accurate quoting
-        let syn_type: syn::Type = syn::parse_str(ty_str).unwrap_or_else(|_| panic!("Failed to parse type string: {}", ty_str));
+        let syn_type: syn::Type = syn::parse_str(ty_str)
+            .unwrap_or_else(|_| panic!("Failed to parse type string: {}", ty_str));
         quote! {
             #name_ident: #syn_type
         }
@@ -1,4 +1,10 @@
+//! Configuration types for selecting and initializing LLM providers.
+
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
+use zeroize::Zeroize;
 
 /// APP site URL
 pub const APP_SITE_URL: &str = "merco.app";
@@ -6,8 +12,13 @@ pub const APP_SITE_URL: &str = "merco.app";
 /// APP site name
 pub const APP_SITE_NAME: &str = "Merco LLM";
 
+/// OpenRouter's OpenAI-compatible API endpoint.
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
 /// Represents the supported LLM providers.
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Deserializes from lowercase names (`"openai"`, `"ollama"`, `"anthropic"`, `"custom"`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Provider {
     /// OpenAI models (via OpenAI API or compatible endpoints like OpenRouter).
     OpenAI,
@@ -16,7 +27,7 @@ pub enum Provider {
     /// Anthropic Claude models.
     Anthropic,
     /// Placeholder for custom or self-hosted models using a specific base URL.
-    Custom, 
+    Custom,
 }
 
 /// Configuration for initializing an LLM provider.
@@ -24,11 +35,155 @@ pub enum Provider {
 pub struct LlmConfig {
     /// The specific provider to use.
     pub provider: Provider,
-    /// The API key required by the provider (if any).
-    pub api_key: Option<String>,
+    /// The API key required by the provider (if any). Redacted in `Debug` output.
+    pub api_key: Option<SecretString>,
     /// The base URL for the provider's API endpoint.
     /// Optional, mainly for `Custom` providers or overriding defaults (e.g., OpenRouter).
     pub base_url: Option<String>,
+    /// How failed HTTP requests (429/5xx, connection errors) are retried.
+    pub retry: RetryPolicy,
+}
+
+/// Retry behaviour for provider HTTP requests.
+///
+/// A request is retried when the server answers with one of `retry_statuses` or the
+/// connection fails before a response arrives. The delay before each retry comes from the
+/// response's `Retry-After` / `x-ratelimit-reset-*` headers when present, otherwise from
+/// exponential backoff (`base_delay * 2^n`). Both are capped at `max_delay`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry when the server gives no hint.
+    pub base_delay: Duration,
+    /// Upper bound for any single delay, including server-provided ones.
+    pub max_delay: Duration,
+    /// Fraction (0.0 to 1.0) of the backoff delay that is randomized, to spread out
+    /// clients retrying at the same time.
+    pub jitter: f64,
+    /// HTTP status codes that are worth retrying.
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            retry_statuses: vec![408, 409, 429, 500, 502, 503, 504, 529],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request exactly once.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the total number of attempts (builder style).
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the base backoff delay (builder style).
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the maximum delay between attempts (builder style).
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets the randomized fraction of the backoff delay (builder style).
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the HTTP status codes that are retried (builder style).
+    pub fn with_retry_statuses(mut self, retry_statuses: Vec<u16>) -> Self {
+        self.retry_statuses = retry_statuses;
+        self
+    }
+
+    /// Returns true if a response with this status should be retried.
+    pub fn is_retryable_status(&self, status: u16) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    /// Exponential backoff delay before retry number `retry` (starting at 0), capped at
+    /// `max_delay`. `random` in `[0, 1)` picks how much of the jitter fraction is removed.
+    pub(crate) fn backoff_delay(&self, retry: u32, random: f64) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(retry));
+        let capped = exponential.min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0) * random.clamp(0.0, 1.0);
+        capped.mul_f64(1.0 - jitter)
+    }
+}
+
+/// A secret such as an API key.
+///
+/// Prints as `***` in `Debug` and `Display` output, and its memory is zeroed when dropped.
+/// The value is only reachable through `expose_secret`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Wraps `secret`.
+    pub fn new(secret: String) -> Self {
+        SecretString(secret)
+    }
+
+    /// Returns the secret value. Call this only where the value is actually sent.
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        SecretString(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        SecretString(secret.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(SecretString)
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
 }
 
 /// Errors that can occur during configuration validation.
@@ -40,6 +195,26 @@ pub enum ConfigError {
     /// Missing base URL required for the `Custom` provider.
     #[error("Missing base URL for custom provider")]
     MissingBaseUrl,
+    /// None of the environment variables `LlmConfig::from_env` looks for is set.
+    #[error("No provider configured in the environment (set OPENAI_API_KEY, ANTHROPIC_API_KEY, OPENROUTER_API_KEY or OLLAMA_HOST)")]
+    NoProviderInEnv,
+    /// An environment variable required by the configuration is not set.
+    #[error("Missing environment variable: {0}")]
+    MissingEnvVar(String),
+    /// The configuration file could not be read.
+    #[error("Failed to read config file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The configuration file is malformed or has an unsupported format.
+    #[error("Invalid config file: {0}")]
+    Parse(String),
+    /// A model alias or default refers to a provider or alias that is not defined.
+    #[error("Unknown {kind} '{name}' in config file")]
+    UnknownReference {
+        /// What kind of entry was referenced ("provider" or "model").
+        kind: &'static str,
+        /// The missing name.
+        name: String,
+    },
 }
 
 impl LlmConfig {
@@ -49,12 +224,13 @@ impl LlmConfig {
             provider,
             api_key: None,
             base_url: None,
+            retry: RetryPolicy::default(),
         }
     }
 
     /// Sets the API key for the configuration (builder style).
     pub fn with_api_key(mut self, api_key: String) -> Self {
-        self.api_key = Some(api_key);
+        self.api_key = Some(SecretString::new(api_key));
         self
     }
 
@@ -64,6 +240,12 @@ impl LlmConfig {
         self
     }
 
+    /// Sets the retry policy for the configuration (builder style).
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// Validates the configuration based on the selected provider's requirements.
     ///
     /// # Errors
@@ -90,4 +272,193 @@ impl LlmConfig {
         }
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Builds a configuration from conventional environment variables, picking the first
+    /// provider with credentials set: `OPENAI_API_KEY`, `ANTHROPIC_API_KEY`,
+    /// `OPENROUTER_API_KEY`, then `OLLAMA_HOST`.
+    ///
+    /// See `from_env_for` for the variables read for each provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::NoProviderInEnv` if none of these variables is set.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Builds a configuration for `provider` from conventional environment variables:
+    ///
+    /// * `OpenAI`: `OPENAI_API_KEY` and optional `OPENAI_BASE_URL`; without an OpenAI key,
+    ///   `OPENROUTER_API_KEY` with the OpenRouter base URL.
+    /// * `Anthropic`: `ANTHROPIC_API_KEY` and optional `ANTHROPIC_BASE_URL`, the API root
+    ///   as the official SDKs expect it (`https://api.anthropic.com`); `/v1` is appended
+    ///   when missing.
+    /// * `Ollama`: optional `OLLAMA_HOST`, as accepted by the Ollama CLI (`host:port` or a URL).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::MissingEnvVar` if a required variable is not set, and
+    /// `ConfigError::MissingBaseUrl` for `Custom`, which has no conventional variables.
+    pub fn from_env_for(provider: Provider) -> Result<Self, ConfigError> {
+        Self::provider_from_env_with(provider, |name| std::env::var(name).ok())
+    }
+
+    fn from_env_with(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let is_set = |name: &str| lookup(name).is_some_and(|value| !value.is_empty());
+        let provider = if is_set("OPENAI_API_KEY") {
+            Provider::OpenAI
+        } else if is_set("ANTHROPIC_API_KEY") {
+            Provider::Anthropic
+        } else if is_set("OPENROUTER_API_KEY") {
+            Provider::OpenAI
+        } else if is_set("OLLAMA_HOST") {
+            Provider::Ollama
+        } else {
+            return Err(ConfigError::NoProviderInEnv);
+        };
+        Self::provider_from_env_with(provider, lookup)
+    }
+
+    fn provider_from_env_with(
+        provider: Provider,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let var = |name: &str| lookup(name).filter(|value| !value.is_empty());
+        let required =
+            |name: &str| var(name).ok_or_else(|| ConfigError::MissingEnvVar(name.to_string()));
+
+        let config = LlmConfig::new(provider.clone());
+        Ok(match provider {
+            Provider::OpenAI => match (var("OPENAI_API_KEY"), var("OPENROUTER_API_KEY")) {
+                (None, Some(openrouter_key)) => config
+                    .with_api_key(openrouter_key)
+                    .with_base_url(OPENROUTER_BASE_URL.to_string()),
+                _ => {
+                    let config = config.with_api_key(required("OPENAI_API_KEY")?);
+                    match var("OPENAI_BASE_URL") {
+                        Some(base_url) => config.with_base_url(base_url),
+                        None => config,
+                    }
+                }
+            },
+            Provider::Anthropic => {
+                let config = config.with_api_key(required("ANTHROPIC_API_KEY")?);
+                match var("ANTHROPIC_BASE_URL") {
+                    Some(base_url) => config.with_base_url(anthropic_base_url(&base_url)),
+                    None => config,
+                }
+            }
+            Provider::Ollama => match var("OLLAMA_HOST") {
+                Some(host) => config.with_base_url(ollama_host_url(&host)),
+                None => config,
+            },
+            Provider::Custom => return Err(ConfigError::MissingBaseUrl),
+        })
+    }
+}
+
+/// Turns an `ANTHROPIC_BASE_URL` value, which the official SDKs give without the API
+/// version, into a base URL ending in `/v1`.
+fn anthropic_base_url(base_url: &str) -> String {
+    let base_url = base_url.trim().trim_end_matches('/');
+    if base_url.ends_with("/v1") {
+        base_url.to_string()
+    } else {
+        format!("{}/v1", base_url)
+    }
+}
+
+/// Turns an `OLLAMA_HOST` value into a base URL the way the Ollama CLI does: a bare
+/// `host[:port]` gets the `http` scheme and port 11434, while URLs are kept as they are.
+fn ollama_host_url(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    if host.contains("://") {
+        return host.to_string();
+    }
+    let has_port = host
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    if has_port {
+        format!("http://{}", host)
+    } else {
+        format!("http://{}:11434", host)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_is_redacted() {
+        let config = LlmConfig::new(Provider::OpenAI).with_api_key("sk-very-secret".to_string());
+        let api_key = config.api_key.as_ref().unwrap();
+
+        assert!(!format!("{:?}", config).contains("sk-very-secret"));
+        assert_eq!(format!("{} {:?}", api_key, api_key), "*** ***");
+        assert_eq!(api_key.expose_secret(), "sk-very-secret");
+    }
+
+    #[test]
+    fn test_from_env_picks_provider_by_conventional_names() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        let config = LlmConfig::from_env_with(env(&[
+            ("OPENAI_API_KEY", ""),
+            ("ANTHROPIC_API_KEY", "sk-ant"),
+        ]))
+        .unwrap();
+        assert_eq!(config.provider, Provider::Anthropic);
+        assert_eq!(config.api_key.unwrap().expose_secret(), "sk-ant");
+
+        let config = LlmConfig::from_env_with(env(&[
+            ("ANTHROPIC_API_KEY", "sk-ant"),
+            ("ANTHROPIC_BASE_URL", "https://proxy.example.com"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            config.base_url.as_deref(),
+            Some("https://proxy.example.com/v1")
+        );
+        assert_eq!(
+            anthropic_base_url("https://proxy.example.com/v1/"),
+            "https://proxy.example.com/v1"
+        );
+
+        let config = LlmConfig::from_env_with(env(&[
+            ("OPENROUTER_API_KEY", "sk-or"),
+            ("OLLAMA_HOST", "gpu-1"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            (config.provider, config.base_url.as_deref()),
+            (Provider::OpenAI, Some(OPENROUTER_BASE_URL))
+        );
+
+        let config = LlmConfig::from_env_with(env(&[("OLLAMA_HOST", "0.0.0.0")])).unwrap();
+        assert_eq!(config.base_url.as_deref(), Some("http://0.0.0.0:11434"));
+        assert_eq!(ollama_host_url("gpu-1:8080"), "http://gpu-1:8080");
+        assert_eq!(
+            ollama_host_url("https://ollama.example.com/"),
+            "https://ollama.example.com"
+        );
+
+        assert!(matches!(
+            LlmConfig::from_env_with(env(&[])),
+            Err(ConfigError::NoProviderInEnv)
+        ));
+        let result = LlmConfig::provider_from_env_with(
+            Provider::Anthropic,
+            env(&[("OPENAI_API_KEY", "sk")]),
+        );
+        assert!(
+            matches!(result, Err(ConfigError::MissingEnvVar(name)) if name == "ANTHROPIC_API_KEY")
+        );
+    }
+}
@@ -0,0 +1,511 @@
+//! Configuration files.
+//!
+//! Provides `ConfigFile`, a serde-deserializable description of several named providers,
+//! model aliases pointing at them, and default request parameters. Files can be JSON, TOML
+//! (feature `toml`) or YAML (feature `yaml`), and any string value may reference environment
+//! variables as `${NAME}` or `${NAME:-default}`, which keeps secrets out of the file.
+//! Unknown keys are rejected, so a misspelled parameter is an error rather than ignored.
+//!
+//! ```toml
+//! default_model = "smart"
+//!
+//! [defaults]
+//! temperature = 0.7
+//!
+//! [providers.openai]
+//! provider = "openai"
+//! api_key = "${OPENAI_API_KEY}"
+//!
+//! [providers.gpu]
+//! provider = "ollama"
+//! base_url = "${OLLAMA_URL:-http://localhost:11434}"
+//!
+//! [models.smart]
+//! provider = "openai"
+//! model = "gpt-4o"
+//!
+//! [models.fast]
+//! provider = "gpu"
+//! model = "qwen3:4b"
+//! params = { max_tokens = 512 }
+//! ```
+
+use crate::config::{ConfigError, LlmConfig, Provider, SecretString};
+use crate::traits::{CompletionRequest, Reasoning};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// The format of a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// JSON.
+    Json,
+    /// TOML.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// YAML.
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Picks the format from a file extension (`json`, `toml`, `yaml` or `yml`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(ConfigFormat::Json),
+            #[cfg(feature = "toml")]
+            "toml" => Some(ConfigFormat::Toml),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A named provider in a configuration file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderSettings {
+    /// The provider type.
+    pub provider: Provider,
+    /// The API key, usually given as `${SOME_ENV_VAR}`.
+    #[serde(default)]
+    pub api_key: Option<SecretString>,
+    /// The base URL, if not the provider's default.
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl ProviderSettings {
+    /// Builds the `LlmConfig` for this provider.
+    pub fn to_llm_config(&self) -> LlmConfig {
+        LlmConfig {
+            api_key: self.api_key.clone(),
+            base_url: self.base_url.clone(),
+            ..LlmConfig::new(self.provider.clone())
+        }
+    }
+}
+
+/// Request parameters applied to requests that do not set them.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestDefaults {
+    /// Default sampling temperature.
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Default maximum number of tokens to generate.
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Default nucleus sampling probability mass.
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    /// Default top-k sampling.
+    #[serde(default)]
+    pub top_k: Option<u32>,
+    /// Default stop sequences.
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    /// Default sampling seed.
+    #[serde(default)]
+    pub seed: Option<i64>,
+    /// Default presence penalty.
+    #[serde(default)]
+    pub presence_penalty: Option<f32>,
+    /// Default frequency penalty.
+    #[serde(default)]
+    pub frequency_penalty: Option<f32>,
+    /// Default reasoning setting.
+    #[serde(default)]
+    pub reasoning: Option<Reasoning>,
+}
+
+impl RequestDefaults {
+    /// Sets every parameter of `request` that is unset and has a default here.
+    pub fn apply(&self, request: &mut CompletionRequest) {
+        request.temperature = request.temperature.or(self.temperature);
+        request.max_tokens = request.max_tokens.or(self.max_tokens);
+        request.top_p = request.top_p.or(self.top_p);
+        request.top_k = request.top_k.or(self.top_k);
+        request.stop = request.stop.take().or_else(|| self.stop.clone());
+        request.seed = request.seed.or(self.seed);
+        request.presence_penalty = request.presence_penalty.or(self.presence_penalty);
+        request.frequency_penalty = request.frequency_penalty.or(self.frequency_penalty);
+        request.reasoning = request.reasoning.or(self.reasoning);
+    }
+
+    /// Sets every parameter that is unset here from `other`.
+    pub fn fill_from(&mut self, other: &RequestDefaults) {
+        self.temperature = self.temperature.or(other.temperature);
+        self.max_tokens = self.max_tokens.or(other.max_tokens);
+        self.top_p = self.top_p.or(other.top_p);
+        self.top_k = self.top_k.or(other.top_k);
+        self.stop = self.stop.take().or_else(|| other.stop.clone());
+        self.seed = self.seed.or(other.seed);
+        self.presence_penalty = self.presence_penalty.or(other.presence_penalty);
+        self.frequency_penalty = self.frequency_penalty.or(other.frequency_penalty);
+        self.reasoning = self.reasoning.or(other.reasoning);
+    }
+}
+
+/// A model alias in a configuration file, e.g. "fast" or "smart".
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelAlias {
+    /// Name of the provider entry serving this alias.
+    pub provider: String,
+    /// The provider's model identifier.
+    pub model: String,
+    /// Parameters applied to requests for this alias, given as its `params` table.
+    #[serde(default)]
+    pub params: RequestDefaults,
+}
+
+impl ModelAlias {
+    /// Creates an alias for `model` on the provider called `provider`, without parameters.
+    pub fn new(provider: String, model: String) -> Self {
+        Self {
+            provider,
+            model,
+            params: RequestDefaults::default(),
+        }
+    }
+
+    /// Sets the parameters applied to requests for this alias (builder style).
+    pub fn with_params(mut self, params: RequestDefaults) -> Self {
+        self.params = params;
+        self
+    }
+}
+
+/// A configuration file describing named providers, model aliases and defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Alias used when a request names no model.
+    #[serde(default)]
+    pub default_model: Option<String>,
+    /// Parameters applied to every request, below the alias' own parameters.
+    #[serde(default)]
+    pub defaults: RequestDefaults,
+    /// Providers by name.
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderSettings>,
+    /// Model aliases by name.
+    #[serde(default)]
+    pub models: HashMap<String, ModelAlias>,
+}
+
+impl ConfigFile {
+    /// Reads and parses a configuration file, picking the format from its extension.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::Io` if the file cannot be read, `ConfigError::MissingEnvVar` for
+    /// unset variables without a default, and `ConfigError::Parse` or
+    /// `ConfigError::UnknownReference` for invalid contents.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path).ok_or_else(|| {
+            ConfigError::Parse(format!(
+                "Unsupported config file extension: {}",
+                path.display()
+            ))
+        })?;
+        Self::parse(&std::fs::read_to_string(path)?, format)
+    }
+
+    /// Parses configuration file contents, interpolating environment variables.
+    ///
+    /// # Errors
+    ///
+    /// Same as `load`, except for I/O errors.
+    pub fn parse(contents: &str, format: ConfigFormat) -> Result<Self, ConfigError> {
+        Self::parse_with(contents, format, |name| std::env::var(name).ok())
+    }
+
+    fn parse_with(
+        contents: &str,
+        format: ConfigFormat,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let parse_error = |e: &dyn std::fmt::Display| ConfigError::Parse(e.to_string());
+        let mut value: JsonValue = match format {
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(|e| parse_error(&e))?,
+            #[cfg(feature = "toml")]
+            ConfigFormat::Toml => toml::from_str(contents).map_err(|e| parse_error(&e))?,
+            #[cfg(feature = "yaml")]
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| parse_error(&e))?,
+        };
+        interpolate_value(&mut value, &lookup)?;
+
+        let file: ConfigFile = serde_json::from_value(value).map_err(|e| parse_error(&e))?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// Checks that aliases refer to defined providers and the default model to a defined alias.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::UnknownReference` for the first dangling name.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for alias in self.models.values() {
+            if !self.providers.contains_key(&alias.provider) {
+                return Err(ConfigError::UnknownReference {
+                    kind: "provider",
+                    name: alias.provider.clone(),
+                });
+            }
+        }
+        match &self.default_model {
+            Some(name) if !self.models.contains_key(name) => Err(ConfigError::UnknownReference {
+                kind: "model",
+                name: name.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Builds the `LlmConfig` of the provider called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::UnknownReference` if no such provider is defined.
+    pub fn provider_config(&self, name: &str) -> Result<LlmConfig, ConfigError> {
+        self.providers
+            .get(name)
+            .map(ProviderSettings::to_llm_config)
+            .ok_or_else(|| ConfigError::UnknownReference {
+                kind: "provider",
+                name: name.to_string(),
+            })
+    }
+}
+
+/// Interpolates environment variables in every string of a parsed file.
+fn interpolate_value(
+    value: &mut JsonValue,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    match value {
+        JsonValue::String(text) => *text = interpolate(text, lookup)?,
+        JsonValue::Array(items) => items
+            .iter_mut()
+            .try_for_each(|item| interpolate_value(item, lookup))?,
+        JsonValue::Object(map) => map
+            .values_mut()
+            .try_for_each(|item| interpolate_value(item, lookup))?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces `${NAME}` and `${NAME:-default}` with environment variable values; `$$` is a literal `$`.
+fn interpolate(
+    text: &str,
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<String, ConfigError> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or_else(|| {
+                ConfigError::Parse(format!("Unterminated '${{' in config value: {}", text))
+            })?;
+            let (name, default) = match body[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&body[..end], None),
+            };
+            let value = lookup(name)
+                .filter(|value| !value.is_empty())
+                .or_else(|| default.map(str::to_string))
+                .ok_or_else(|| ConfigError::MissingEnvVar(name.to_string()))?;
+            result.push_str(&value);
+            rest = &body[end + 1..];
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::ReasoningEffort;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "OPENAI_API_KEY" => Some("sk-from-env".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolates_env_vars() {
+        assert_eq!(
+            interpolate("Bearer ${OPENAI_API_KEY}", &env).unwrap(),
+            "Bearer sk-from-env"
+        );
+        assert_eq!(
+            interpolate("${OLLAMA_URL:-http://localhost:11434}/v1", &env).unwrap(),
+            "http://localhost:11434/v1"
+        );
+        assert_eq!(interpolate("$$HOME and $5", &env).unwrap(), "$HOME and $5");
+        assert!(
+            matches!(interpolate("${MISSING}", &env), Err(ConfigError::MissingEnvVar(name)) if name == "MISSING")
+        );
+        assert!(matches!(
+            interpolate("${OPENAI_API_KEY", &env),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_formats_parse_to_the_same_config() {
+        let json = r#"{
+            "default_model": "fast",
+            "defaults": { "temperature": 0.5 },
+            "providers": {
+                "openai": { "provider": "openai", "api_key": "${OPENAI_API_KEY}" },
+                "gpu": { "provider": "ollama", "base_url": "${GPU_URL:-http://gpu-1:11434}" }
+            },
+            "models": {
+                "fast": {
+                    "provider": "gpu",
+                    "model": "qwen3:4b",
+                    "params": { "max_tokens": 512, "reasoning": "disabled" }
+                },
+                "smart": { "provider": "openai", "model": "o4-mini", "params": { "reasoning": { "effort": "high" } } }
+            }
+        }"#;
+        let toml = r#"
+            default_model = "fast"
+            defaults = { temperature = 0.5 }
+
+            [providers.openai]
+            provider = "openai"
+            api_key = "${OPENAI_API_KEY}"
+
+            [providers.gpu]
+            provider = "ollama"
+            base_url = "${GPU_URL:-http://gpu-1:11434}"
+
+            [models.fast]
+            provider = "gpu"
+            model = "qwen3:4b"
+            params = { max_tokens = 512, reasoning = "disabled" }
+
+            [models.smart]
+            provider = "openai"
+            model = "o4-mini"
+            params.reasoning = { effort = "high" }
+        "#;
+        let yaml = r#"
+default_model: fast
+defaults:
+  temperature: 0.5
+providers:
+  openai:
+    provider: openai
+    api_key: ${OPENAI_API_KEY}
+  gpu:
+    provider: ollama
+    base_url: ${GPU_URL:-http://gpu-1:11434}
+models:
+  fast: { provider: gpu, model: "qwen3:4b", params: { max_tokens: 512, reasoning: disabled } }
+  smart: { provider: openai, model: o4-mini, params: { reasoning: { effort: high } } }
+"#;
+
+        let mut sources = vec![(json, ConfigFormat::Json)];
+        #[cfg(feature = "toml")]
+        sources.push((toml, ConfigFormat::Toml));
+        #[cfg(feature = "yaml")]
+        sources.push((yaml, ConfigFormat::Yaml));
+
+        for (contents, format) in sources {
+            let file = ConfigFile::parse_with(contents, format, env).unwrap();
+
+            assert_eq!(file.default_model.as_deref(), Some("fast"));
+            assert_eq!(file.defaults.temperature, Some(0.5));
+            let openai = file.provider_config("openai").unwrap();
+            assert_eq!(openai.provider, Provider::OpenAI);
+            assert_eq!(openai.api_key.unwrap().expose_secret(), "sk-from-env");
+            assert_eq!(
+                file.provider_config("gpu").unwrap().base_url.as_deref(),
+                Some("http://gpu-1:11434")
+            );
+            assert_eq!(file.models["fast"].params.max_tokens, Some(512));
+            assert_eq!(
+                file.models["fast"].params.reasoning,
+                Some(Reasoning::Disabled)
+            );
+            assert_eq!(
+                file.models["smart"].params.reasoning,
+                Some(Reasoning::Effort(ReasoningEffort::High))
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_dangling_references() {
+        let json = r#"{ "models": { "fast": { "provider": "gpu", "model": "qwen3:4b" } } }"#;
+        let result = ConfigFile::parse_with(json, ConfigFormat::Json, env);
+        assert!(
+            matches!(result, Err(ConfigError::UnknownReference { kind: "provider", name }) if name == "gpu")
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        let misspelled = [
+            r#"{ "defaults": { "temprature": 0.5 } }"#,
+            r#"{ "providers": { "gpu": { "provider": "ollama", "base-url": "http://gpu-1:11434" } } }"#,
+            r#"{ "providers": { "gpu": { "provider": "ollama" } },
+                 "models": { "fast": { "provider": "gpu", "model": "qwen3:4b", "max_tokens": 512 } } }"#,
+            r#"{ "providers": { "gpu": { "provider": "ollama" } },
+                 "models": { "fast": { "provider": "gpu", "model": "qwen3:4b", "params": { "temprature": 0.5 } } } }"#,
+            r#"{ "default-model": "fast" }"#,
+        ];
+        for json in misspelled {
+            let result = ConfigFile::parse_with(json, ConfigFormat::Json, env);
+            let message = match result {
+                Err(ConfigError::Parse(message)) => message,
+                other => panic!(
+                    "expected a parse error for {}, got {:?}",
+                    json,
+                    other.map(|_| ())
+                ),
+            };
+            assert!(message.contains("unknown field"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_defaults_fill_only_unset_fields() {
+        let defaults = RequestDefaults {
+            temperature: Some(0.2),
+            max_tokens: Some(100),
+            ..Default::default()
+        };
+        let mut request = CompletionRequest {
+            temperature: Some(0.9),
+            ..Default::default()
+        };
+        defaults.apply(&mut request);
+        assert_eq!(
+            (request.temperature, request.max_tokens),
+            (Some(0.9), Some(100))
+        );
+    }
+}
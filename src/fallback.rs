@@ -0,0 +1,383 @@
+//! Fallback chains.
+//!
+//! Provides `FallbackProvider`, an `LlmProvider` that tries an ordered list of backends
+//! and moves on to the next one when a backend fails with an error the `FallbackPolicy`
+//! considers worth falling back on (rate limits, server errors, timeouts, context length).
+
+use crate::traits::{
+    CompletionRequest, CompletionResponse, CompletionStream, ErrorKind, LlmProvider, ModelInfo,
+    ProviderError,
+};
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+
+/// One backend in a fallback chain.
+#[derive(Clone)]
+pub struct FallbackEntry {
+    /// The provider to send the request to.
+    pub provider: Arc<dyn LlmProvider>,
+    /// Model to use with this provider instead of the request's model, if set.
+    pub model: Option<String>,
+    /// Optional label reported in `FallbackReport`, e.g. "openrouter".
+    pub name: Option<String>,
+}
+
+impl FallbackEntry {
+    /// Creates an entry for `provider`, optionally overriding the request's model.
+    pub fn new(provider: Arc<dyn LlmProvider>, model: Option<String>) -> Self {
+        Self {
+            provider,
+            model,
+            name: None,
+        }
+    }
+
+    /// Sets the label reported for this entry (builder style).
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+}
+
+impl From<(Arc<dyn LlmProvider>, Option<String>)> for FallbackEntry {
+    fn from((provider, model): (Arc<dyn LlmProvider>, Option<String>)) -> Self {
+        Self::new(provider, model)
+    }
+}
+
+/// Decides which errors make a `FallbackProvider` try its next entry.
+/// Any other error is returned to the caller straight away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackPolicy {
+    /// HTTP statuses of `ProviderError::ApiError` that fall back.
+    pub statuses: Vec<u16>,
+    /// Fall back when the request could not be sent (connection failures, timeouts).
+    pub on_request_errors: bool,
+    /// Fall back when the prompt exceeds the model's context window, so a later entry
+    /// with a larger model can serve it.
+    pub on_context_length: bool,
+}
+
+impl Default for FallbackPolicy {
+    fn default() -> Self {
+        FallbackPolicy {
+            statuses: vec![408, 409, 429, 500, 502, 503, 504, 529],
+            on_request_errors: true,
+            on_context_length: true,
+        }
+    }
+}
+
+impl FallbackPolicy {
+    /// Returns true if `error` should make the chain try its next entry.
+    pub fn should_fall_back(&self, error: &ProviderError) -> bool {
+        match error {
+            ProviderError::ApiError { status, kind, .. } => {
+                self.statuses.contains(status)
+                    || (self.on_context_length && *kind == ErrorKind::ContextLengthExceeded)
+            }
+            ProviderError::RateLimited { .. } => self.statuses.contains(&429),
+            ProviderError::RequestError(_) => {
+                self.on_request_errors
+                    && matches!(error.kind(), ErrorKind::Timeout | ErrorKind::Connection)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// An entry that failed before the request was served.
+#[derive(Debug)]
+pub struct FallbackFailure {
+    /// Position of the entry in the chain.
+    pub index: usize,
+    /// The entry's label, if any.
+    pub name: Option<String>,
+    /// The model that was requested from the entry.
+    pub model: String,
+    /// The error the entry returned.
+    pub error: ProviderError,
+}
+
+/// Describes which entry of a fallback chain served a request.
+#[derive(Debug)]
+pub struct FallbackReport {
+    /// Position of the serving entry in the chain.
+    pub index: usize,
+    /// The serving entry's label, if any.
+    pub name: Option<String>,
+    /// The model that served the request.
+    pub model: String,
+    /// Entries tried before it, in order.
+    pub failures: Vec<FallbackFailure>,
+}
+
+/// An `LlmProvider` that tries a chain of backends in order.
+///
+/// Each entry receives the request with its model override applied. When an entry fails
+/// with an error the policy accepts, the next entry is tried; when every entry fails, the
+/// last error is returned. For streams, only the initial connect falls back: errors after
+/// the stream has started are passed through. `list_models` walks the chain the same way
+/// and returns the models of the first entry that answers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{get_provider, FallbackEntry, FallbackProvider, LlmConfig, Provider};
+///
+/// # fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// let openai = get_provider(LlmConfig::new(Provider::OpenAI).with_api_key("sk-...".to_string()))?;
+/// let ollama = get_provider(LlmConfig::new(Provider::Ollama))?;
+///
+/// let provider = FallbackProvider::new(vec![
+///     FallbackEntry::new(openai, Some("gpt-4o-mini".to_string())).with_name("openai".to_string()),
+///     FallbackEntry::new(ollama, Some("qwen3:4b".to_string())).with_name("ollama".to_string()),
+/// ]);
+/// // let (response, report) = provider.completion_with_report(request).await?;
+/// // println!("served by {:?} ({})", report.name, report.model);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FallbackProvider {
+    entries: Vec<FallbackEntry>,
+    policy: FallbackPolicy,
+}
+
+impl FallbackProvider {
+    /// Creates a fallback chain with the default `FallbackPolicy`.
+    pub fn new(entries: Vec<FallbackEntry>) -> Self {
+        Self {
+            entries,
+            policy: FallbackPolicy::default(),
+        }
+    }
+
+    /// Sets the policy deciding which errors fall back (builder style).
+    pub fn with_policy(mut self, policy: FallbackPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The entries of the chain, in the order they are tried.
+    pub fn entries(&self) -> &[FallbackEntry] {
+        &self.entries
+    }
+
+    /// Like `completion`, but also reports which entry served the request.
+    pub async fn completion_with_report(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<(CompletionResponse, FallbackReport), ProviderError> {
+        self.run(request, |provider, request| async move {
+            provider.completion(request).await
+        })
+        .await
+    }
+
+    /// Like `completion_stream`, but also reports which entry opened the stream.
+    pub async fn completion_stream_with_report(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<(CompletionStream, FallbackReport), ProviderError> {
+        self.run(request, |provider, request| async move {
+            provider.completion_stream(request).await
+        })
+        .await
+    }
+
+    /// Calls `call` on each entry in turn until one succeeds or fails with an error
+    /// the policy does not fall back on.
+    async fn run<T, F, Fut>(
+        &self,
+        request: CompletionRequest,
+        call: F,
+    ) -> Result<(T, FallbackReport), ProviderError>
+    where
+        F: Fn(Arc<dyn LlmProvider>, CompletionRequest) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        let mut failures = Vec::new();
+        let last = self.entries.len().checked_sub(1).ok_or_else(|| {
+            ProviderError::ConfigError("FallbackProvider has no entries".to_string())
+        })?;
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let mut request = request.clone();
+            if let Some(model) = &entry.model {
+                request.model = model.clone();
+            }
+            let model = request.model.clone();
+
+            match call(entry.provider.clone(), request).await {
+                Ok(value) => {
+                    let report = FallbackReport {
+                        index,
+                        name: entry.name.clone(),
+                        model,
+                        failures,
+                    };
+                    return Ok((value, report));
+                }
+                Err(error) if index < last && self.policy.should_fall_back(&error) => {
+                    failures.push(FallbackFailure {
+                        index,
+                        name: entry.name.clone(),
+                        model,
+                        error,
+                    });
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        unreachable!("the last entry always returns")
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FallbackProvider {
+    /// Sends the request to the first entry that serves it.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        self.completion_with_report(request)
+            .await
+            .map(|(response, _)| response)
+    }
+
+    /// Opens the stream on the first entry that accepts it.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        self.completion_stream_with_report(request)
+            .await
+            .map(|(stream, _)| stream)
+    }
+
+    /// Lists the models of the first entry that answers.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let last = self.entries.len().checked_sub(1).ok_or_else(|| {
+            ProviderError::ConfigError("FallbackProvider has no entries".to_string())
+        })?;
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            match entry.provider.list_models().await {
+                Err(error) if index < last && self.policy.should_fall_back(&error) => {}
+                result => return result,
+            }
+        }
+        unreachable!("the last entry always returns")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+    use crate::traits::ChatMessage;
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            model: "default".to_string(),
+            messages: vec![ChatMessage::user("Hi".to_string())],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_falls_back_on_retryable_errors_and_reports_backend() {
+        let limited = MockProvider::failing(429, "rate limited");
+        let too_long =
+            MockProvider::failing(400, "This model's maximum context length is 8192 tokens");
+        let healthy = MockProvider::new();
+        let provider = FallbackProvider::new(vec![
+            FallbackEntry::new(limited.clone(), Some("small".to_string()))
+                .with_name("primary".to_string()),
+            FallbackEntry::new(too_long.clone(), None),
+            FallbackEntry::new(healthy.clone(), Some("large".to_string()))
+                .with_name("backup".to_string()),
+        ]);
+
+        let (response, report) =
+            futures::executor::block_on(provider.completion_with_report(request())).unwrap();
+
+        assert_eq!(response.content(), Some("large"));
+        assert_eq!(
+            (report.index, report.name.as_deref(), report.model.as_str()),
+            (2, Some("backup"), "large")
+        );
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(report.failures[0].model, "small");
+        assert_eq!(report.failures[1].model, "default");
+        assert_eq!(healthy.models(), vec!["large".to_string()]);
+    }
+
+    #[test]
+    fn test_stops_on_errors_outside_the_policy() {
+        let unauthorized = MockProvider::failing(401, "invalid api key");
+        let healthy = MockProvider::new();
+        let provider = FallbackProvider::new(vec![
+            FallbackEntry::new(unauthorized, None),
+            FallbackEntry::new(healthy.clone(), None),
+        ]);
+
+        let result = futures::executor::block_on(provider.completion(request()));
+        assert!(matches!(
+            result,
+            Err(ProviderError::ApiError { status: 401, .. })
+        ));
+        assert_eq!(healthy.calls(), 0);
+
+        let policy = FallbackPolicy {
+            on_context_length: false,
+            ..Default::default()
+        };
+        let too_long = ProviderError::api_error(
+            400,
+            "prompt is too long: 210000 tokens > 200000 maximum".to_string(),
+            Default::default(),
+        );
+        assert!(!policy.should_fall_back(&too_long));
+        assert!(FallbackPolicy::default().should_fall_back(&too_long));
+    }
+
+    #[test]
+    fn test_returns_last_error_when_every_entry_fails() {
+        let provider = FallbackProvider::new(vec![
+            FallbackEntry::new(MockProvider::failing(503, "unavailable"), None),
+            FallbackEntry::new(MockProvider::failing(502, "bad gateway"), None),
+        ]);
+        let result = futures::executor::block_on(provider.completion(request()));
+        assert!(matches!(
+            result,
+            Err(ProviderError::ApiError { status: 502, .. })
+        ));
+
+        let empty = FallbackProvider::new(Vec::new());
+        let result = futures::executor::block_on(empty.completion(request()));
+        assert!(matches!(result, Err(ProviderError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_list_models_falls_back_like_completion() {
+        let provider = FallbackProvider::new(vec![
+            FallbackEntry::new(MockProvider::failing(503, "unavailable"), None),
+            FallbackEntry::new(MockProvider::replying("backup"), None),
+        ]);
+        let models = futures::executor::block_on(provider.list_models()).unwrap();
+        assert_eq!(models[0].id, "backup");
+
+        let provider = FallbackProvider::new(vec![
+            FallbackEntry::new(MockProvider::failing(401, "invalid api key"), None),
+            FallbackEntry::new(MockProvider::replying("backup"), None),
+        ]);
+        let result = futures::executor::block_on(provider.list_models());
+        assert!(matches!(
+            result,
+            Err(ProviderError::ApiError { status: 401, .. })
+        ));
+    }
+}
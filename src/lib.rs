@@ -4,31 +4,61 @@
 //! through a common configuration and trait implementation.
 
 pub mod config;
+pub mod config_file;
+pub mod fallback;
+pub mod limiter;
 pub mod providers;
-pub mod traits;
+pub mod registry;
+pub mod router;
+#[cfg(test)]
+mod testing;
 pub mod tools;
+pub mod traits;
+#[cfg(feature = "typed")]
+pub mod typed;
 
-pub use config::{ConfigError, LlmConfig, Provider};
-pub use providers::{OllamaProvider, OpenAIProvider};
+pub use config::{ConfigError, LlmConfig, Provider, RetryPolicy, SecretString};
+pub use config_file::{ConfigFile, ConfigFormat, ModelAlias, ProviderSettings, RequestDefaults};
+pub use fallback::{
+    FallbackEntry, FallbackFailure, FallbackPolicy, FallbackProvider, FallbackReport,
+};
+pub use limiter::{
+    estimate_request_tokens, RateLimitMode, RateLimitedProvider, RateLimiter, RateLimits,
+    RatePermit,
+};
+pub use providers::{AnthropicProvider, OllamaProvider, OllamaToolMode, OpenAIProvider};
+pub use registry::{ModelRegistry, ResolvedModel};
+pub use router::{Deployment, Router, RoutingStrategy};
 pub use traits::{
-    ChatMessage, CompletionKind, CompletionRequest, CompletionResponse, CompletionStream,
-    CompletionStreamChunk, JsonSchema, LlmProvider, ProviderError, StreamContentDelta, Tool,
-    ToolCallFunction, ToolCallRequest, ToolCallStreamDelta, TokenUsage,
+    ApiErrorDetails, ChatMessage, CompletionChoice, CompletionKind, CompletionRequest,
+    CompletionResponse, CompletionStream, CompletionStreamChunk, ContentPart, EmbeddingProvider,
+    EmbeddingRequest, EmbeddingResponse, ErrorKind, FinishReason, JsonSchema, LlmProvider,
+    MessageContent, ModelInfo, ModelPricing, ProviderError, Reasoning, ReasoningBlock,
+    ReasoningEffort, ResponseFormat, StreamContentDelta, TokenUsage, Tool, ToolCallFunction,
+    ToolCallRequest, ToolCallStreamDelta, ToolChoice,
 };
 
-// Re-export tool utilities 
-pub use tools::{execute_tool, get_all_tools, get_tools_by_names, register_tool, ToolExecutor, ToolRegistry};
+// Re-export tool utilities
+pub use tools::{
+    execute_tool, get_all_tools, get_tools_by_names, register_tool, ToolExecutor, ToolRegistry,
+};
 
 // Conditionally re-export the macro if the feature is enabled
 #[cfg(feature = "macros")]
 pub use tools::merco_tool;
 
+// Typed completions, plus schemars so callers can derive `JsonSchema` for their types
+#[cfg(feature = "typed")]
+pub use schemars;
+#[cfg(feature = "typed")]
+pub use typed::{response_format_for, TypedCompletionExt};
+
 // Optional: A factory function to create a provider instance based on config
 use std::sync::Arc;
 
 /// Creates a provider instance based on the provided configuration.
 ///
-/// This function validates the configuration and returns a dynamic dispatch trait object (`Arc<dyn LlmProvider>`) 
+/// This function validates the configuration and returns a dynamic dispatch trait object (`Arc<dyn LlmProvider>`)
 /// allowing interaction with the selected provider through the common `LlmProvider` trait.
 ///
 /// # Arguments
@@ -37,7 +67,8 @@ use std::sync::Arc;
 ///
 /// # Errors
 ///
-/// Returns `ProviderError::ConfigError` if the configuration is invalid for the selected provider.
+/// Returns `ProviderError::ConfigError` if the configuration is invalid for the selected provider,
+/// including API keys that are not valid HTTP header values.
 /// Returns `ProviderError::Unsupported` if the selected provider is not yet implemented.
 ///
 /// # Examples
@@ -65,12 +96,58 @@ use std::sync::Arc;
 /// // let response = provider.completion(request).await;
 /// ```
 pub fn get_provider(config: LlmConfig) -> Result<Arc<dyn LlmProvider>, ProviderError> {
-    config.validate().map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+    config
+        .validate()
+        .map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+
+    match config.provider {
+        Provider::OpenAI => Ok(Arc::new(OpenAIProvider::try_new(config)?)),
+        Provider::Ollama => Ok(Arc::new(OllamaProvider::try_new(config)?)),
+        Provider::Anthropic => Ok(Arc::new(AnthropicProvider::try_new(config)?)),
+        Provider::Custom => Err(ProviderError::Unsupported(
+            "Custom provider logic not yet implemented".to_string(),
+        )),
+    }
+}
+
+/// Creates an embedding provider instance based on the provided configuration.
+///
+/// Works like `get_provider`, returning an `Arc<dyn EmbeddingProvider>` for providers
+/// with an embeddings endpoint (OpenAI-compatible APIs and Ollama).
+///
+/// # Errors
+///
+/// Returns `ProviderError::ConfigError` if the configuration is invalid for the selected provider.
+/// Returns `ProviderError::Unsupported` if the selected provider has no embeddings API.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{get_embedding_provider, EmbeddingRequest, LlmConfig, Provider};
+///
+/// # async fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// let provider = get_embedding_provider(LlmConfig::new(Provider::Ollama))?;
+/// let request = EmbeddingRequest::new("nomic-embed-text".to_string(), vec!["Hello world".to_string()]);
+/// let response = provider.embed(request).await?;
+/// println!("{} dimensions", response.embeddings[0].len());
+/// # Ok(())
+/// # }
+/// ```
+pub fn get_embedding_provider(
+    config: LlmConfig,
+) -> Result<Arc<dyn EmbeddingProvider>, ProviderError> {
+    config
+        .validate()
+        .map_err(|e| ProviderError::ConfigError(e.to_string()))?;
 
     match config.provider {
-        Provider::OpenAI => Ok(Arc::new(OpenAIProvider::new(config))),
-        Provider::Ollama => Ok(Arc::new(OllamaProvider::new(config))),
-        Provider::Anthropic => Err(ProviderError::Unsupported("Anthropic provider not yet implemented".to_string())),
-        Provider::Custom => Err(ProviderError::Unsupported("Custom provider logic not yet implemented".to_string())),
+        Provider::OpenAI => Ok(Arc::new(OpenAIProvider::try_new(config)?)),
+        Provider::Ollama => Ok(Arc::new(OllamaProvider::try_new(config)?)),
+        Provider::Anthropic => Err(ProviderError::Unsupported(
+            "Anthropic does not provide an embeddings API".to_string(),
+        )),
+        Provider::Custom => Err(ProviderError::Unsupported(
+            "Custom provider logic not yet implemented".to_string(),
+        )),
     }
 }
@@ -0,0 +1,570 @@
+//! Client-side rate limiting.
+//!
+//! Provides `RateLimiter`, which enforces requests-per-minute and tokens-per-minute budgets
+//! before requests are sent, and `RateLimitedProvider`, an `LlmProvider` wrapper applying
+//! it. Prompt tokens are estimated up front and the estimate is settled with the
+//! `TokenUsage` of the response. One limiter can be shared by several wrappers (e.g. every
+//! worker using the same API key) so they draw from the same budget.
+
+use crate::traits::{
+    CompletionRequest, CompletionResponse, CompletionStream, CompletionStreamChunk, ContentPart,
+    LlmProvider, MessageContent, ProviderError, StreamContentDelta,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Rough token cost assumed for each image part.
+pub const ESTIMATED_IMAGE_TOKENS: u32 = 765;
+
+/// Tokens assumed for each message's role and formatting.
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+/// Requests-per-minute and tokens-per-minute budgets. `None` leaves a dimension unlimited,
+/// while a budget of 0 refuses every request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    /// Maximum requests per minute.
+    pub requests_per_minute: Option<u32>,
+    /// Maximum tokens (prompt plus completion) per minute.
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl RateLimits {
+    /// Creates limits with both dimensions unlimited.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the requests-per-minute budget (builder style).
+    pub fn with_rpm(mut self, requests_per_minute: u32) -> Self {
+        self.requests_per_minute = Some(requests_per_minute);
+        self
+    }
+
+    /// Sets the tokens-per-minute budget (builder style).
+    pub fn with_tpm(mut self, tokens_per_minute: u32) -> Self {
+        self.tokens_per_minute = Some(tokens_per_minute);
+        self
+    }
+}
+
+/// What a `RateLimiter` does when the budget is exhausted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Wait for budget, serving waiting requests in arrival order.
+    #[default]
+    Queue,
+    /// Return `ProviderError::RateLimited` with the time until budget is available.
+    FailFast,
+}
+
+/// A budget that refills continuously at `capacity` per minute.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+}
+
+impl Bucket {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            capacity: f64::from(per_minute),
+            available: f64::from(per_minute),
+        }
+    }
+
+    fn refill(&mut self, elapsed: Duration) {
+        self.available =
+            (self.available + self.capacity * elapsed.as_secs_f64() / 60.0).min(self.capacity);
+    }
+
+    /// Time until `amount` is available, or `None` when an empty bucket never refills.
+    /// Amounts above the capacity only need a full bucket.
+    fn wait_for(&self, amount: f64) -> Option<Duration> {
+        if self.capacity <= 0.0 {
+            return None;
+        }
+        let missing = amount.min(self.capacity) - self.available;
+        if missing <= 0.0 {
+            Some(Duration::ZERO)
+        } else {
+            Some(Duration::from_secs_f64(missing * 60.0 / self.capacity))
+        }
+    }
+}
+
+/// The request and token buckets of one limit key.
+#[derive(Debug)]
+struct Budget {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+    updated: Instant,
+}
+
+impl Budget {
+    fn new(limits: RateLimits, now: Instant) -> Self {
+        Self {
+            requests: limits.requests_per_minute.map(Bucket::new),
+            tokens: limits.tokens_per_minute.map(Bucket::new),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.updated = now;
+        self.requests
+            .iter_mut()
+            .chain(self.tokens.iter_mut())
+            .for_each(|bucket| bucket.refill(elapsed));
+    }
+
+    /// Takes one request and `tokens` from the budget, or returns how long to wait
+    /// (`None` when the budget is 0 and waiting would never end).
+    fn try_take(&mut self, tokens: u32, now: Instant) -> Result<(), Option<Duration>> {
+        self.refill(now);
+        let wait = [
+            self.requests.as_ref().map(|bucket| bucket.wait_for(1.0)),
+            self.tokens
+                .as_ref()
+                .map(|bucket| bucket.wait_for(f64::from(tokens))),
+        ]
+        .into_iter()
+        .flatten()
+        .try_fold(Duration::ZERO, |longest, wait| {
+            wait.map(|wait| longest.max(wait))
+        })
+        .ok_or(None)?;
+        if !wait.is_zero() {
+            return Err(Some(wait));
+        }
+        if let Some(bucket) = &mut self.requests {
+            bucket.available -= 1.0;
+        }
+        if let Some(bucket) = &mut self.tokens {
+            bucket.available -= f64::from(tokens);
+        }
+        Ok(())
+    }
+
+    /// Corrects the token bucket once the real usage of a request is known.
+    fn settle(&mut self, estimated: u32, actual: u32) {
+        if let Some(bucket) = &mut self.tokens {
+            bucket.available =
+                (bucket.available + f64::from(estimated) - f64::from(actual)).min(bucket.capacity);
+        }
+    }
+}
+
+/// Enforces `RateLimits` before requests are sent.
+///
+/// The default limits form one budget shared by all requests; models given their own
+/// limits with `with_model_limits` get a separate budget.
+#[derive(Debug)]
+pub struct RateLimiter {
+    default_limits: RateLimits,
+    model_limits: HashMap<String, RateLimits>,
+    mode: RateLimitMode,
+    budgets: Mutex<HashMap<String, Budget>>,
+    queues: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter applying `limits` to every request, queueing when they are exhausted.
+    pub fn new(limits: RateLimits) -> Self {
+        Self {
+            default_limits: limits,
+            model_limits: HashMap::new(),
+            mode: RateLimitMode::default(),
+            budgets: Mutex::new(HashMap::new()),
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Gives requests for `model` their own budget (builder style).
+    pub fn with_model_limits(mut self, model: String, limits: RateLimits) -> Self {
+        self.model_limits.insert(model, limits);
+        self
+    }
+
+    /// Sets whether exhausted budgets queue or fail (builder style).
+    pub fn with_mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The budget key and limits that apply to `model`.
+    fn limits_for(&self, model: &str) -> (String, RateLimits) {
+        match self.model_limits.get(model) {
+            Some(limits) => (model.to_string(), *limits),
+            None => (String::new(), self.default_limits),
+        }
+    }
+
+    /// Takes budget for a request to `model` expected to use `tokens`, waiting in line or
+    /// failing depending on the mode. Returns the permit used to settle the real usage.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::RateLimited` in `RateLimitMode::FailFast` when the budget is
+    /// exhausted, and in either mode when it is 0.
+    pub async fn acquire(&self, model: &str, tokens: u32) -> Result<RatePermit<'_>, ProviderError> {
+        let (key, limits) = self.limits_for(model);
+        let queue = self
+            .queues
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        // Tokio's mutex is fair, so waiters take budget in the order they arrived.
+        let _turn = queue.lock().await;
+
+        loop {
+            let now = Instant::now();
+            let taken = self
+                .budgets
+                .lock()
+                .unwrap()
+                .entry(key.clone())
+                .or_insert_with(|| Budget::new(limits, now))
+                .try_take(tokens, now);
+            match (taken, self.mode) {
+                (Ok(()), _) => {
+                    return Ok(RatePermit {
+                        limiter: self,
+                        key,
+                        estimated: tokens,
+                    })
+                }
+                (Err(None), _) => {
+                    return Err(ProviderError::RateLimited {
+                        message: format!(
+                            "Client-side rate limit for '{}' allows no requests",
+                            model
+                        ),
+                        retry_after: None,
+                    });
+                }
+                (Err(Some(wait)), RateLimitMode::FailFast) => {
+                    return Err(ProviderError::RateLimited {
+                        message: format!("Client-side rate limit reached for '{}'", model),
+                        retry_after: Some(wait),
+                    });
+                }
+                (Err(Some(wait)), RateLimitMode::Queue) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+}
+
+/// Budget taken for one request; `settle` corrects it with the real token usage.
+#[derive(Debug)]
+pub struct RatePermit<'a> {
+    limiter: &'a RateLimiter,
+    key: String,
+    estimated: u32,
+}
+
+impl RatePermit<'_> {
+    /// Replaces the token estimate with the `actual` tokens used.
+    pub fn settle(self, actual: u32) {
+        if let Some(budget) = self.limiter.budgets.lock().unwrap().get_mut(&self.key) {
+            budget.settle(self.estimated, actual);
+        }
+    }
+}
+
+/// Estimates the tokens a request will use: about four characters per token for the
+/// prompt and tool definitions, plus `max_tokens` for the completion when set.
+pub fn estimate_request_tokens(request: &CompletionRequest) -> u32 {
+    let chars_to_tokens = |chars: usize| u32::try_from(chars.div_ceil(4)).unwrap_or(u32::MAX);
+
+    let messages: u32 = request
+        .messages
+        .iter()
+        .map(|message| {
+            let content = match &message.content {
+                Some(MessageContent::Text(text)) => chars_to_tokens(text.len()),
+                Some(MessageContent::Parts(parts)) => parts
+                    .iter()
+                    .map(|part| match part {
+                        ContentPart::Text { text } => chars_to_tokens(text.len()),
+                        _ => ESTIMATED_IMAGE_TOKENS,
+                    })
+                    .sum(),
+                None => 0,
+            };
+            let tool_calls: u32 = message
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| {
+                    chars_to_tokens(call.function.name.len() + call.function.arguments.len())
+                })
+                .sum();
+            MESSAGE_OVERHEAD_TOKENS + content + tool_calls
+        })
+        .sum();
+    let tools: u32 = request
+        .tools
+        .iter()
+        .flatten()
+        .map(|tool| {
+            chars_to_tokens(
+                serde_json::to_string(tool)
+                    .map(|json| json.len())
+                    .unwrap_or_default(),
+            )
+        })
+        .sum();
+
+    messages
+        .saturating_add(tools)
+        .saturating_add(request.max_tokens.unwrap_or(0))
+}
+
+/// An `LlmProvider` wrapper that takes budget from a `RateLimiter` before each request.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{get_provider, LlmConfig, Provider, RateLimitedProvider, RateLimiter, RateLimits};
+/// use std::sync::Arc;
+///
+/// # fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// // One limiter per API key, shared by every worker using it.
+/// let limiter = Arc::new(RateLimiter::new(RateLimits::new().with_rpm(500).with_tpm(200_000)));
+/// let provider = get_provider(LlmConfig::new(Provider::OpenAI).with_api_key("sk-...".to_string()))?;
+/// let limited = RateLimitedProvider::new(provider, limiter.clone());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RateLimitedProvider {
+    inner: Arc<dyn LlmProvider>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedProvider {
+    /// Wraps `inner` so its requests draw from `limiter`.
+    pub fn new(inner: Arc<dyn LlmProvider>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for RateLimitedProvider {
+    /// Takes budget for the estimated tokens, then settles it with the response's usage.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let permit = self
+            .limiter
+            .acquire(&request.model, estimate_request_tokens(&request))
+            .await?;
+        let response = self.inner.completion(request).await?;
+        if let Some(usage) = response.usage {
+            permit.settle(usage.total_tokens);
+        }
+        Ok(response)
+    }
+
+    /// Takes budget for the estimated tokens, then settles it with the usage of the
+    /// stream's final chunk. When the provider reports no usage, it is settled once the
+    /// stream is dropped with the prompt estimate plus an estimate of the streamed output.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let model = request.model.clone();
+        let estimated = estimate_request_tokens(&request);
+        let prompt = estimated.saturating_sub(request.max_tokens.unwrap_or(0));
+        let permit = self.limiter.acquire(&model, estimated).await?;
+        let key = permit.key.clone();
+        drop(permit);
+
+        let stream = self.inner.completion_stream(request).await?;
+        let mut usage = StreamUsage {
+            limiter: self.limiter.clone(),
+            key,
+            estimated,
+            prompt,
+            output_chars: 0,
+            settled: false,
+        };
+        Ok(Box::pin(stream.map(move |chunk| {
+            usage.record(&chunk);
+            chunk
+        })))
+    }
+}
+
+/// Settles the token estimate of a stream, with the reported usage or, when the stream is
+/// dropped without one, with an estimate of what was streamed.
+struct StreamUsage {
+    limiter: Arc<RateLimiter>,
+    key: String,
+    estimated: u32,
+    prompt: u32,
+    output_chars: usize,
+    settled: bool,
+}
+
+impl StreamUsage {
+    fn record(&mut self, chunk: &Result<CompletionStreamChunk, ProviderError>) {
+        let Ok(chunk) = chunk else { return };
+        if let Some(usage) = chunk.usage {
+            self.settle(usage.total_tokens);
+        }
+        self.output_chars += match &chunk.delta {
+            StreamContentDelta::Text(text) | StreamContentDelta::Reasoning(text) => text.len(),
+            StreamContentDelta::ToolCallDelta(deltas) => deltas
+                .iter()
+                .filter_map(|delta| delta.function.as_ref())
+                .map(|function| {
+                    let name = function.name.as_ref().map_or(0, String::len);
+                    name + function.arguments.as_ref().map_or(0, String::len)
+                })
+                .sum(),
+            StreamContentDelta::ReasoningBlock(_) => 0,
+        };
+    }
+
+    fn settle(&mut self, actual: u32) {
+        if !self.settled {
+            self.settled = true;
+            RatePermit {
+                limiter: &self.limiter,
+                key: self.key.clone(),
+                estimated: self.estimated,
+            }
+            .settle(actual);
+        }
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        let output = u32::try_from(self.output_chars.div_ceil(4)).unwrap_or(u32::MAX);
+        self.settle(self.prompt.saturating_add(output));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+    use crate::traits::ChatMessage;
+
+    fn request(model: &str, max_tokens: u32) -> CompletionRequest {
+        CompletionRequest {
+            model: model.to_string(),
+            messages: vec![ChatMessage::user("12345678".to_string())],
+            max_tokens: Some(max_tokens),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_estimates_prompt_and_completion_tokens() {
+        assert_eq!(estimate_request_tokens(&request("m", 100)), 4 + 2 + 100);
+
+        let image = CompletionRequest {
+            messages: vec![ChatMessage::user_parts(vec![ContentPart::image_url(
+                "https://example.com/a.png".to_string(),
+            )])],
+            ..Default::default()
+        };
+        assert_eq!(estimate_request_tokens(&image), 4 + ESTIMATED_IMAGE_TOKENS);
+    }
+
+    #[test]
+    fn test_fail_fast_when_requests_per_minute_are_used_up() {
+        let limiter =
+            RateLimiter::new(RateLimits::new().with_rpm(2)).with_mode(RateLimitMode::FailFast);
+        let provider = RateLimitedProvider::new(MockProvider::with_usage(1), Arc::new(limiter));
+
+        for _ in 0..2 {
+            assert!(futures::executor::block_on(provider.completion(request("m", 1))).is_ok());
+        }
+        match futures::executor::block_on(provider.completion(request("m", 1))) {
+            Err(ProviderError::RateLimited {
+                retry_after: Some(wait),
+                ..
+            }) => {
+                assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+            }
+            other => panic!("expected RateLimited, got {:?}", other.map(|r| r.usage)),
+        }
+    }
+
+    #[test]
+    fn test_usage_settles_token_estimate_per_model_budget() {
+        let limiter = RateLimiter::new(RateLimits::new().with_tpm(1000))
+            .with_model_limits("small".to_string(), RateLimits::new().with_tpm(150))
+            .with_mode(RateLimitMode::FailFast);
+        let provider = RateLimitedProvider::new(MockProvider::with_usage(10), Arc::new(limiter));
+
+        // Each request is estimated at 86 tokens but only uses 10; without settling,
+        // a 150 token budget would only fit one of them.
+        for _ in 0..5 {
+            assert!(futures::executor::block_on(provider.completion(request("small", 80))).is_ok());
+        }
+
+        let mut budget = Budget::new(RateLimits::new().with_tpm(100), Instant::now());
+        let now = budget.updated;
+        assert!(budget.try_take(86, now).is_ok());
+        assert!(budget.try_take(86, now).is_err());
+        budget.settle(86, 10);
+        assert!(budget.try_take(86, now).is_ok());
+    }
+
+    #[test]
+    fn test_zero_budget_refuses_requests() {
+        for mode in [RateLimitMode::Queue, RateLimitMode::FailFast] {
+            let limiter = RateLimiter::new(RateLimits::new().with_rpm(0)).with_mode(mode);
+            let provider = RateLimitedProvider::new(MockProvider::with_usage(1), Arc::new(limiter));
+            let result = futures::executor::block_on(provider.completion(request("m", 1)));
+            assert!(matches!(
+                result,
+                Err(ProviderError::RateLimited {
+                    retry_after: None,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_stream_without_usage_settles_streamed_estimate() {
+        let limiter = Arc::new(
+            RateLimiter::new(RateLimits::new().with_tpm(1000)).with_mode(RateLimitMode::FailFast),
+        );
+        // Replies with the model name ("m", one token) and reports no usage.
+        let provider = RateLimitedProvider::new(MockProvider::new(), limiter.clone());
+
+        let stream =
+            futures::executor::block_on(provider.completion_stream(request("m", 500))).unwrap();
+        assert_eq!(
+            futures::executor::block_on(stream.collect::<Vec<_>>()).len(),
+            1
+        );
+
+        // The 500 reserved completion tokens are returned; prompt (6) and output (1) stay charged.
+        let available = limiter.budgets.lock().unwrap()[""]
+            .tokens
+            .as_ref()
+            .unwrap()
+            .available;
+        assert!(
+            (993.0..994.0).contains(&available),
+            "available: {}",
+            available
+        );
+    }
+}
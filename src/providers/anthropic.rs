@@ -0,0 +1,1258 @@
+//!
+//! Anthropic Provider Implementation
+//!
+//! This module provides the `AnthropicProvider` struct and its implementation
+//! of the `LlmProvider` trait for interacting with Anthropic's Messages API
+//! (`/v1/messages`), including tool use and streaming over named SSE events.
+
+use crate::config::{LlmConfig, Provider};
+use crate::providers::errors::{read_api_error, ParsedApiError};
+use crate::providers::params::{ensure_supported, SamplingParam};
+use crate::providers::passthrough::{apply_extra_headers, merge_extra_body};
+use crate::providers::retry::send_with_retry;
+use crate::providers::sse::{decode_sse_stream, SseEvent};
+use crate::traits::{
+    ChatMessage, ChatMessageRole, CompletionChoice, CompletionRequest, CompletionResponse,
+    CompletionStream, CompletionStreamChunk, ContentPart, FinishReason, JsonSchema, LlmProvider,
+    MessageContent, ModelInfo, ProviderError, Reasoning, ReasoningBlock, ReasoningEffort,
+    ResponseFormat, StreamContentDelta, TokenUsage, Tool, ToolCallFunction,
+    ToolCallFunctionStreamDelta, ToolCallRequest, ToolCallStreamDelta, ToolChoice,
+};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json, Value as JsonValue};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Base URL for the official Anthropic API.
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+/// API version sent in the `anthropic-version` header.
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+/// Anthropic requires `max_tokens`; this is used when the request does not set it.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+/// Thinking budgets used for `ReasoningEffort` levels, as Anthropic only accepts a token budget.
+const THINKING_BUDGET_LOW: u32 = 1024;
+const THINKING_BUDGET_MEDIUM: u32 = 4096;
+const THINKING_BUDGET_HIGH: u32 = 16384;
+/// Default request timeout in seconds.
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
+// --- Anthropic Specific API Structures ---
+
+#[derive(Serialize, Debug)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: JsonSchema,
+}
+
+#[derive(Serialize, Debug)]
+struct AnthropicMessagesRequest {
+    model: String,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<JsonValue>,
+}
+
+#[derive(Serialize, Debug)]
+struct AnthropicMessage {
+    role: &'static str, // "user" or "assistant"
+    content: Vec<AnthropicContentBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicImageSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: JsonValue,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    Image {
+        source: AnthropicImageSource,
+    },
+    Thinking {
+        thinking: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    /// Block types we do not map are skipped on the way in.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)] // Allow unused fields from API response
+struct AnthropicMessagesResponse {
+    // id: String, // Often unused
+    content: Vec<AnthropicContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+// --- Streaming Structures ---
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    MessageStart {
+        message: AnthropicStreamMessageStart,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: AnthropicContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: AnthropicBlockDelta,
+    },
+    ContentBlockStop {
+        index: usize,
+    },
+    MessageDelta {
+        delta: AnthropicMessageDelta,
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: AnthropicErrorDetail,
+    },
+    /// Event types added to the API later are skipped rather than ending the stream.
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicStreamMessageStart {
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    /// Delta types we do not map (e.g. `citations_delta`).
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicMessageDelta {
+    stop_reason: Option<String>,
+}
+
+// --- Model Listing Structures ---
+
+#[derive(Deserialize, Debug)]
+struct AnthropicModelList {
+    data: Vec<AnthropicModel>,
+    #[serde(default)]
+    has_more: bool,
+    last_id: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicModel {
+    id: String,
+    display_name: Option<String>,
+}
+
+// For parsing Anthropic's specific error structure
+#[derive(Deserialize, Debug)]
+struct AnthropicErrorResponse {
+    error: AnthropicErrorDetail,
+}
+#[derive(Deserialize, Debug)]
+struct AnthropicErrorDetail {
+    message: String,
+    #[serde(rename = "type", default)]
+    error_type: Option<String>,
+}
+
+/// Per-stream state used while translating Anthropic events into generic chunks.
+#[derive(Debug, Default)]
+struct AnthropicStreamState {
+    /// Maps Anthropic content block indexes to tool call indexes.
+    tool_indexes: HashMap<usize, usize>,
+    /// Prompt tokens reported by `message_start`.
+    input_tokens: u32,
+    /// Thinking blocks still being streamed, by content block index, as (text, signature).
+    thinking: HashMap<usize, (String, Option<String>)>,
+}
+
+// --- Provider Implementation ---
+
+/// Provides interaction with Anthropic's Messages API.
+///
+/// Supports chat completion and tool use, both streaming and non-streaming.
+#[derive(Debug, Clone)]
+pub struct AnthropicProvider {
+    config: LlmConfig,
+    client: Client,
+    base_url: String,
+    /// Content type, API key and version headers, validated at construction.
+    headers: HeaderMap,
+}
+
+impl AnthropicProvider {
+    /// Creates a new Anthropic provider instance from the given configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::MissingConfig` if the API key is missing, and
+    /// `ProviderError::ConfigError` if it is not a valid header value or the HTTP client fails to build.
+    pub fn try_new(config: LlmConfig) -> Result<Self, ProviderError> {
+        let api_key = config.api_key.as_ref().ok_or_else(|| {
+            ProviderError::MissingConfig("Anthropic provider requires an API key".to_string())
+        })?;
+
+        let mut api_key_header = HeaderValue::from_str(api_key.expose_secret()).map_err(|_| {
+            ProviderError::ConfigError(
+                "Anthropic API key is not a valid HTTP header value".to_string(),
+            )
+        })?;
+        api_key_header.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert("x-api-key", api_key_header);
+        headers.insert(
+            "anthropic-version",
+            HeaderValue::from_static(ANTHROPIC_API_VERSION),
+        );
+
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| ANTHROPIC_BASE_URL.to_string());
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| {
+                ProviderError::ConfigError(format!("Failed to build HTTP client: {}", e))
+            })?;
+
+        Ok(Self {
+            config,
+            client,
+            base_url,
+            headers,
+        })
+    }
+
+    /// Creates a new Anthropic provider instance from the given configuration.
+    /// Panics if the configuration is invalid; use `try_new` to handle the error instead.
+    pub fn new(config: LlmConfig) -> Self {
+        Self::try_new(config)
+            .unwrap_or_else(|e| panic!("Failed to create Anthropic provider: {}", e))
+    }
+
+    /// Returns the provider's HTTP headers plus the request's extra headers.
+    fn build_headers(
+        &self,
+        extra_headers: Option<&HashMap<String, String>>,
+    ) -> Result<HeaderMap, ProviderError> {
+        let mut headers = self.headers.clone();
+        apply_extra_headers(&mut headers, extra_headers)?;
+        Ok(headers)
+    }
+
+    /// Maps the generic Tool structure to the Anthropic-specific format.
+    fn map_tools_to_anthropic(tools: Option<&Vec<Tool>>) -> Option<Vec<AnthropicTool>> {
+        tools.map(|ts| {
+            ts.iter()
+                .map(|tool| AnthropicTool {
+                    name: tool.name.clone(),
+                    description: tool.description.clone(),
+                    input_schema: tool.parameters.clone(),
+                })
+                .collect()
+        })
+    }
+
+    /// Maps the generic tool choice to Anthropic's `tool_choice` object.
+    /// Only sent when tools are present and a choice was made.
+    fn map_tool_choice(request: &CompletionRequest) -> Option<JsonValue> {
+        request.tools.as_ref()?;
+        let choice = match request.tool_choice.as_ref()? {
+            ToolChoice::Auto => json!({ "type": "auto" }),
+            ToolChoice::None => json!({ "type": "none" }),
+            ToolChoice::Required => json!({ "type": "any" }),
+            ToolChoice::Function { name } => json!({ "type": "tool", "name": name }),
+        };
+        Some(choice)
+    }
+
+    /// Splits the generic conversation into Anthropic's top-level `system` prompt and
+    /// a list of user/assistant messages made of content blocks.
+    ///
+    /// Tool results are sent as `tool_result` blocks inside a user message, and
+    /// consecutive messages with the same role are merged since the API expects
+    /// the roles to alternate.
+    fn map_messages(
+        messages: &[ChatMessage],
+    ) -> Result<(Option<String>, Vec<AnthropicMessage>), ProviderError> {
+        let mut system_parts: Vec<String> = Vec::new();
+        let mut mapped: Vec<AnthropicMessage> = Vec::new();
+
+        for message in messages {
+            let (role, blocks) = match message.role {
+                ChatMessageRole::System => {
+                    if let Some(text) = message.text() {
+                        system_parts.push(text);
+                    }
+                    continue;
+                }
+                ChatMessageRole::User => match &message.content {
+                    Some(MessageContent::Parts(parts)) => {
+                        ("user", parts.iter().map(Self::map_content_part).collect())
+                    }
+                    _ => (
+                        "user",
+                        vec![AnthropicContentBlock::Text {
+                            text: message.text().unwrap_or_default(),
+                        }],
+                    ),
+                },
+                ChatMessageRole::Assistant => {
+                    // Thinking blocks must come first. Anthropic rejects unsigned thinking,
+                    // so blocks without a signature (e.g. from another provider) are dropped.
+                    let mut blocks: Vec<AnthropicContentBlock> = message
+                        .reasoning_blocks
+                        .iter()
+                        .filter_map(|block| match block {
+                            ReasoningBlock::Thinking {
+                                thinking,
+                                signature: Some(signature),
+                            } => Some(AnthropicContentBlock::Thinking {
+                                thinking: thinking.clone(),
+                                signature: Some(signature.clone()),
+                            }),
+                            ReasoningBlock::Thinking {
+                                signature: None, ..
+                            } => None,
+                            ReasoningBlock::Redacted { data } => {
+                                Some(AnthropicContentBlock::RedactedThinking { data: data.clone() })
+                            }
+                        })
+                        .collect();
+                    if let Some(text) = message.text().filter(|t| !t.is_empty()) {
+                        blocks.push(AnthropicContentBlock::Text { text });
+                    }
+                    for call in message.tool_calls.iter().flatten() {
+                        let input = if call.function.arguments.trim().is_empty() {
+                            JsonValue::Object(serde_json::Map::new())
+                        } else {
+                            serde_json::from_str(&call.function.arguments).map_err(|e| {
+                                ProviderError::ToolFormatError(format!(
+                                    "Arguments for tool call '{}' are not valid JSON: {}",
+                                    call.id, e
+                                ))
+                            })?
+                        };
+                        blocks.push(AnthropicContentBlock::ToolUse {
+                            id: call.id.clone(),
+                            name: call.function.name.clone(),
+                            input,
+                        });
+                    }
+                    ("assistant", blocks)
+                }
+                ChatMessageRole::Tool => {
+                    let tool_use_id = message.tool_call_id.clone().ok_or_else(|| {
+                        ProviderError::ToolFormatError(
+                            "Tool message is missing tool_call_id".to_string(),
+                        )
+                    })?;
+                    let content = message.text().unwrap_or_default();
+                    (
+                        "user",
+                        vec![AnthropicContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                        }],
+                    )
+                }
+            };
+
+            if blocks.is_empty() {
+                continue;
+            }
+            match mapped.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => mapped.push(AnthropicMessage {
+                    role,
+                    content: blocks,
+                }),
+            }
+        }
+
+        let system = if system_parts.is_empty() {
+            None
+        } else {
+            Some(system_parts.join("\n\n"))
+        };
+        Ok((system, mapped))
+    }
+
+    /// Maps a generic content part to an Anthropic `text` or `image` block.
+    /// Base64 `data:` URLs are sent inline rather than as URL sources.
+    fn map_content_part(part: &ContentPart) -> AnthropicContentBlock {
+        let source = match (part, part.base64_image()) {
+            (ContentPart::Text { text }, _) => {
+                return AnthropicContentBlock::Text { text: text.clone() }
+            }
+            (_, Some((media_type, data))) => AnthropicImageSource::Base64 {
+                media_type: media_type.to_string(),
+                data: data.to_string(),
+            },
+            (ContentPart::ImageUrl { url }, None) => AnthropicImageSource::Url { url: url.clone() },
+            (ContentPart::ImageBase64 { media_type, data }, None) => AnthropicImageSource::Base64 {
+                media_type: media_type.clone(),
+                data: data.clone(),
+            },
+        };
+        AnthropicContentBlock::Image { source }
+    }
+
+    /// Anthropic has no `response_format` parameter, so JSON output is requested
+    /// through a system prompt instruction instead.
+    fn response_format_instruction(
+        response_format: Option<&ResponseFormat>,
+    ) -> Result<Option<String>, ProviderError> {
+        let instruction = match response_format {
+            None | Some(ResponseFormat::Text) => return Ok(None),
+            Some(ResponseFormat::JsonObject) => {
+                "Respond only with a single valid JSON object, without any surrounding text or markdown.".to_string()
+            }
+            Some(ResponseFormat::JsonSchema { schema, .. }) => format!(
+                "Respond only with a single valid JSON object matching this JSON Schema, without any surrounding text or markdown:\n{}",
+                serde_json::to_string_pretty(schema)?
+            ),
+        };
+        Ok(Some(instruction))
+    }
+
+    /// Maps the reasoning controls to Anthropic's `thinking` parameter, returning it with
+    /// the thinking budget. Effort levels are translated to fixed budgets.
+    fn map_thinking(reasoning: Option<Reasoning>) -> (Option<JsonValue>, Option<u32>) {
+        let budget = match reasoning {
+            None => return (None, None),
+            Some(Reasoning::Disabled) => return (Some(json!({ "type": "disabled" })), None),
+            Some(Reasoning::BudgetTokens(budget)) => budget,
+            Some(Reasoning::Effort(ReasoningEffort::Low)) => THINKING_BUDGET_LOW,
+            Some(Reasoning::Effort(ReasoningEffort::Medium)) => THINKING_BUDGET_MEDIUM,
+            Some(Reasoning::Effort(ReasoningEffort::High)) => THINKING_BUDGET_HIGH,
+        };
+        (
+            Some(json!({ "type": "enabled", "budget_tokens": budget })),
+            Some(budget),
+        )
+    }
+
+    /// Rejects the settings Anthropic does not allow together with extended thinking:
+    /// a `max_tokens` that leaves no room beyond the thinking `budget`, a temperature
+    /// other than 1, `top_k`, and forcing a tool call.
+    fn ensure_thinking_compatible(
+        request: &CompletionRequest,
+        budget: u32,
+    ) -> Result<(), ProviderError> {
+        if let Some(max_tokens) = request
+            .max_tokens
+            .filter(|&max_tokens| max_tokens <= budget)
+        {
+            return Err(ProviderError::Unsupported(format!(
+                "Anthropic requires max_tokens ({}) to be greater than the thinking budget ({})",
+                max_tokens, budget
+            )));
+        }
+        let mut conflicts = Vec::new();
+        if request
+            .temperature
+            .is_some_and(|temperature| temperature != 1.0)
+        {
+            conflicts.push("temperature");
+        }
+        if request.top_k.is_some() {
+            conflicts.push("top_k");
+        }
+        if request.tools.is_some()
+            && matches!(
+                request.tool_choice,
+                Some(ToolChoice::Required | ToolChoice::Function { .. })
+            )
+        {
+            conflicts.push("a forced tool_choice");
+        }
+        if conflicts.is_empty() {
+            return Ok(());
+        }
+        Err(ProviderError::Unsupported(format!(
+            "Anthropic does not support {} with thinking enabled",
+            conflicts.join(", ")
+        )))
+    }
+
+    /// Builds the Anthropic request body from the generic request.
+    /// The Messages API has no seed, penalties, logit bias or multiple choices.
+    fn build_request(
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<AnthropicMessagesRequest, ProviderError> {
+        ensure_supported(
+            "Anthropic",
+            request,
+            &[
+                SamplingParam::Seed,
+                SamplingParam::PresencePenalty,
+                SamplingParam::FrequencyPenalty,
+                SamplingParam::LogitBias,
+                SamplingParam::MultipleChoices,
+            ],
+        )?;
+        let (mut system, messages) = Self::map_messages(&request.messages)?;
+        if let Some(instruction) =
+            Self::response_format_instruction(request.response_format.as_ref())?
+        {
+            system = Some(match system {
+                Some(existing) => format!("{}\n\n{}", existing, instruction),
+                None => instruction,
+            });
+        }
+        // The thinking budget counts towards `max_tokens`, so leave room for the answer.
+        let (thinking, thinking_budget) = Self::map_thinking(request.reasoning);
+        if let Some(budget) = thinking_budget {
+            Self::ensure_thinking_compatible(request, budget)?;
+        }
+        let max_tokens = request
+            .max_tokens
+            .unwrap_or_else(|| DEFAULT_MAX_TOKENS + thinking_budget.unwrap_or(0));
+
+        Ok(AnthropicMessagesRequest {
+            model: request.model.clone(),
+            messages,
+            system,
+            max_tokens,
+            temperature: request.temperature,
+            top_p: request.top_p,
+            top_k: request.top_k,
+            stop_sequences: request.stop.clone(),
+            stream,
+            tools: Self::map_tools_to_anthropic(request.tools.as_ref()),
+            tool_choice: Self::map_tool_choice(request),
+            thinking,
+        })
+    }
+
+    /// Maps the Anthropic usage structure to the generic TokenUsage structure.
+    fn map_usage(input_tokens: u32, output_tokens: u32) -> TokenUsage {
+        TokenUsage {
+            prompt_tokens: input_tokens,
+            completion_tokens: output_tokens,
+            total_tokens: input_tokens + output_tokens,
+        }
+    }
+
+    /// Maps Anthropic's `stop_reason` onto the normalized `FinishReason`.
+    fn map_stop_reason(stop_reason: Option<String>) -> Option<FinishReason> {
+        stop_reason.map(|reason| match reason.as_str() {
+            "end_turn" => FinishReason::Stop,
+            "stop_sequence" => FinishReason::StopSequence,
+            "max_tokens" | "model_context_window_exceeded" => FinishReason::Length,
+            "tool_use" => FinishReason::ToolCalls,
+            "refusal" => FinishReason::ContentFilter,
+            _ => FinishReason::Other(reason),
+        })
+    }
+
+    /// Builds the single choice of a response from its content blocks,
+    /// keeping text written before or between tool use blocks.
+    fn map_choice(
+        content: Vec<AnthropicContentBlock>,
+        finish_reason: Option<FinishReason>,
+    ) -> CompletionChoice {
+        let mut text = String::new();
+        let mut reasoning = String::new();
+        let mut reasoning_blocks = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in content {
+            match block {
+                AnthropicContentBlock::Text { text: t } => text.push_str(&t),
+                AnthropicContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => {
+                    reasoning.push_str(&thinking);
+                    reasoning_blocks.push(ReasoningBlock::Thinking {
+                        thinking,
+                        signature,
+                    });
+                }
+                AnthropicContentBlock::RedactedThinking { data } => {
+                    reasoning_blocks.push(ReasoningBlock::Redacted { data });
+                }
+                AnthropicContentBlock::ToolUse { id, name, input } => {
+                    tool_calls.push(ToolCallRequest::new_function_call(
+                        id,
+                        ToolCallFunction {
+                            name,
+                            arguments: input.to_string(),
+                        },
+                    ));
+                }
+                AnthropicContentBlock::ToolResult { .. }
+                | AnthropicContentBlock::Image { .. }
+                | AnthropicContentBlock::Unknown => {}
+            }
+        }
+        CompletionChoice {
+            index: 0,
+            content: if text.is_empty() { None } else { Some(text) },
+            tool_calls,
+            refusal: None,
+            reasoning: if reasoning.is_empty() {
+                None
+            } else {
+                Some(reasoning)
+            },
+            reasoning_blocks,
+            finish_reason,
+        }
+    }
+
+    /// Converts a non-success HTTP response into a `ProviderError::ApiError`,
+    /// keeping Anthropic's error `type` (e.g. `overloaded_error`).
+    async fn api_error(res: reqwest::Response) -> ProviderError {
+        read_api_error(res, |body| {
+            let error = serde_json::from_str::<AnthropicErrorResponse>(body)
+                .ok()?
+                .error;
+            Some(ParsedApiError {
+                message: error.message,
+                error_type: error.error_type,
+                ..Default::default()
+            })
+        })
+        .await
+    }
+
+    /// Translates a single Anthropic stream event into a generic chunk, if it carries anything.
+    fn map_stream_event(
+        event: AnthropicStreamEvent,
+        state: &mut AnthropicStreamState,
+    ) -> Result<Option<CompletionStreamChunk>, ProviderError> {
+        let chunk = match event {
+            AnthropicStreamEvent::MessageStart { message } => {
+                state.input_tokens = message.usage.map(|u| u.input_tokens).unwrap_or(0);
+                None
+            }
+            AnthropicStreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                AnthropicContentBlock::ToolUse { id, name, .. } => {
+                    let tool_index = state.tool_indexes.len();
+                    state.tool_indexes.insert(index, tool_index);
+                    Some(CompletionStreamChunk {
+                        delta: StreamContentDelta::ToolCallDelta(vec![ToolCallStreamDelta {
+                            index: tool_index,
+                            id: Some(id),
+                            function: Some(ToolCallFunctionStreamDelta {
+                                name: Some(name),
+                                arguments: None,
+                            }),
+                        }]),
+                        usage: None,
+                        finish_reason: None,
+                    })
+                }
+                AnthropicContentBlock::Text { text } if !text.is_empty() => {
+                    Some(CompletionStreamChunk {
+                        delta: StreamContentDelta::Text(text),
+                        usage: None,
+                        finish_reason: None,
+                    })
+                }
+                AnthropicContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => {
+                    state.thinking.insert(index, (thinking.clone(), signature));
+                    (!thinking.is_empty()).then_some(CompletionStreamChunk {
+                        delta: StreamContentDelta::Reasoning(thinking),
+                        usage: None,
+                        finish_reason: None,
+                    })
+                }
+                AnthropicContentBlock::RedactedThinking { data } => Some(CompletionStreamChunk {
+                    delta: StreamContentDelta::ReasoningBlock(ReasoningBlock::Redacted { data }),
+                    usage: None,
+                    finish_reason: None,
+                }),
+                _ => None,
+            },
+            AnthropicStreamEvent::ContentBlockDelta { index, delta } => match delta {
+                AnthropicBlockDelta::TextDelta { text } => Some(CompletionStreamChunk {
+                    delta: StreamContentDelta::Text(text),
+                    usage: None,
+                    finish_reason: None,
+                }),
+                AnthropicBlockDelta::InputJsonDelta { partial_json } => {
+                    let tool_index = *state.tool_indexes.get(&index).ok_or_else(|| {
+                        ProviderError::StreamError(format!(
+                            "Received input_json_delta for unknown content block {}",
+                            index
+                        ))
+                    })?;
+                    Some(CompletionStreamChunk {
+                        delta: StreamContentDelta::ToolCallDelta(vec![ToolCallStreamDelta {
+                            index: tool_index,
+                            id: None,
+                            function: Some(ToolCallFunctionStreamDelta {
+                                name: None,
+                                arguments: Some(partial_json),
+                            }),
+                        }]),
+                        usage: None,
+                        finish_reason: None,
+                    })
+                }
+                AnthropicBlockDelta::ThinkingDelta { thinking } => {
+                    if let Some((text, _)) = state.thinking.get_mut(&index) {
+                        text.push_str(&thinking);
+                    }
+                    Some(CompletionStreamChunk {
+                        delta: StreamContentDelta::Reasoning(thinking),
+                        usage: None,
+                        finish_reason: None,
+                    })
+                }
+                AnthropicBlockDelta::SignatureDelta { signature } => {
+                    if let Some((_, block_signature)) = state.thinking.get_mut(&index) {
+                        *block_signature = Some(signature);
+                    }
+                    None
+                }
+                AnthropicBlockDelta::Unknown => None,
+            },
+            // A finished thinking block is emitted whole, signature included, for the history.
+            AnthropicStreamEvent::ContentBlockStop { index } => {
+                state
+                    .thinking
+                    .remove(&index)
+                    .map(|(thinking, signature)| CompletionStreamChunk {
+                        delta: StreamContentDelta::ReasoningBlock(ReasoningBlock::Thinking {
+                            thinking,
+                            signature,
+                        }),
+                        usage: None,
+                        finish_reason: None,
+                    })
+            }
+            AnthropicStreamEvent::MessageDelta { delta, usage } => {
+                let output_tokens = usage.map(|u| u.output_tokens).unwrap_or(0);
+                Some(CompletionStreamChunk {
+                    delta: StreamContentDelta::Text("".to_string()), // Empty delta for final info
+                    usage: Some(Self::map_usage(state.input_tokens, output_tokens)),
+                    finish_reason: Self::map_stop_reason(delta.stop_reason),
+                })
+            }
+            AnthropicStreamEvent::Error { error } => {
+                return Err(ProviderError::StreamError(error.message));
+            }
+            AnthropicStreamEvent::MessageStop
+            | AnthropicStreamEvent::Ping
+            | AnthropicStreamEvent::Unknown => None,
+        };
+        Ok(chunk)
+    }
+
+    /// Parses one SSE event into generic stream chunks.
+    /// The event type is also carried in the JSON `type` field, so only the data is used.
+    fn process_event(
+        event: SseEvent,
+        state: &mut AnthropicStreamState,
+    ) -> Result<Vec<CompletionStreamChunk>, ProviderError> {
+        if event.data.is_empty() {
+            return Ok(Vec::new());
+        }
+        let anthropic_event: AnthropicStreamEvent = serde_json::from_str(&event.data)?;
+        Ok(Self::map_stream_event(anthropic_event, state)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    /// Generates a non-streaming completion, handling potential tool use.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        if self.config.provider != Provider::Anthropic {
+            return Err(ProviderError::ConfigError(
+                "Invalid provider configured for AnthropicProvider".to_string(),
+            ));
+        }
+
+        let anthropic_request = Self::build_request(&request, false)?;
+
+        let url = format!("{}/messages", self.base_url);
+        let headers = self.build_headers(request.extra_headers.as_ref())?;
+        let body = merge_extra_body(&anthropic_request, request.extra_body.as_ref())?;
+
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&body),
+        )
+        .await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
+        }
+
+        let anthropic_response: AnthropicMessagesResponse = res.json().await?;
+
+        let usage = anthropic_response
+            .usage
+            .map(|u| Self::map_usage(u.input_tokens, u.output_tokens));
+        let finish_reason = Self::map_stop_reason(anthropic_response.stop_reason);
+        let choice = Self::map_choice(anthropic_response.content, finish_reason);
+
+        Ok(CompletionResponse {
+            choices: vec![choice],
+            usage,
+        })
+    }
+
+    /// Generates a streaming completion, including streamed tool use.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        if self.config.provider != Provider::Anthropic {
+            return Err(ProviderError::ConfigError(
+                "Invalid provider configured for AnthropicProvider".to_string(),
+            ));
+        }
+
+        let anthropic_request = Self::build_request(&request, true)?;
+
+        let url = format!("{}/messages", self.base_url);
+        let headers = self.build_headers(request.extra_headers.as_ref())?;
+        let body = merge_extra_body(&anthropic_request, request.extra_body.as_ref())?;
+
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&body),
+        )
+        .await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
+        }
+
+        // Tool indexes and prompt usage carried across events; owned by the stream handler
+        let mut state = AnthropicStreamState::default();
+
+        Ok(decode_sse_stream(res.bytes_stream(), move |event| {
+            Self::process_event(event, &mut state)
+        }))
+    }
+
+    /// Lists models through `GET /models`, following pagination.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let url = format!("{}/models", self.base_url);
+        let mut models = Vec::new();
+        let mut after_id: Option<String> = None;
+
+        loop {
+            let mut query = vec![("limit", "1000".to_string())];
+            if let Some(after_id) = after_id.take() {
+                query.push(("after_id", after_id));
+            }
+            let headers = self.build_headers(None)?;
+            let res = send_with_retry(
+                &self.config.retry,
+                self.client.get(&url).headers(headers).query(&query),
+            )
+            .await?;
+
+            if !res.status().is_success() {
+                return Err(Self::api_error(res).await);
+            }
+
+            let page: AnthropicModelList = res.json().await?;
+            models.extend(page.data.into_iter().map(|model| ModelInfo {
+                id: model.id,
+                name: model.display_name,
+                ..Default::default()
+            }));
+            match page.last_id {
+                Some(last_id) if page.has_more => after_id = Some(last_id),
+                _ => return Ok(models),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use futures::stream::{self, StreamExt};
+
+    #[test]
+    fn test_try_new_rejects_invalid_api_key() {
+        let config = LlmConfig::new(Provider::Anthropic).with_api_key("sk-ant-\u{7f}".to_string());
+        assert!(matches!(
+            AnthropicProvider::try_new(config),
+            Err(ProviderError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_messages_extracts_system_and_tool_blocks() {
+        let call = ToolCallRequest::new_function_call(
+            "toolu_1".to_string(),
+            ToolCallFunction {
+                name: "add".to_string(),
+                arguments: r#"{"a":1,"b":2}"#.to_string(),
+            },
+        );
+        let messages = vec![
+            ChatMessage::system("Be brief.".to_string()),
+            ChatMessage::user("What is 1 + 2?".to_string()),
+            ChatMessage::assistant(Some("Let me add.".to_string()), Some(vec![call])),
+            ChatMessage::tool_result("toolu_1".to_string(), "3".to_string()),
+        ];
+
+        let (system, mapped) = AnthropicProvider::map_messages(&messages).unwrap();
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        assert_eq!(mapped.len(), 3);
+        assert_eq!(mapped[1].role, "assistant");
+        assert!(matches!(
+            &mapped[1].content[1],
+            AnthropicContentBlock::ToolUse { name, input, .. } if name == "add" && input["b"] == 2
+        ));
+        assert_eq!(mapped[2].role, "user");
+        assert!(matches!(
+            &mapped[2].content[0],
+            AnthropicContentBlock::ToolResult { tool_use_id, content } if tool_use_id == "toolu_1" && content == "3"
+        ));
+    }
+
+    #[test]
+    fn test_stream_events_split_across_chunks() {
+        let events = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":10,\"output_tokens\":1}}}\n\n",
+            "event: content_block_start\n",
+            "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"add\",\"input\":{}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"a\\\":1}\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":5}}\n\n",
+        );
+        let (first, second) = events.split_at(120);
+
+        let bytes = stream::iter(vec![
+            Ok(Bytes::copy_from_slice(first.as_bytes())),
+            Ok(Bytes::copy_from_slice(second.as_bytes())),
+        ]);
+        let mut state = AnthropicStreamState::default();
+        let chunk_stream = decode_sse_stream(bytes, move |event| {
+            AnthropicProvider::process_event(event, &mut state)
+        });
+        let chunks: Vec<CompletionStreamChunk> =
+            futures::executor::block_on(chunk_stream.collect::<Vec<_>>())
+                .into_iter()
+                .map(|chunk| chunk.unwrap())
+                .collect();
+
+        assert_eq!(chunks.len(), 3);
+        match &chunks[1].delta {
+            StreamContentDelta::ToolCallDelta(deltas) => {
+                assert_eq!(deltas[0].index, 0);
+                assert_eq!(
+                    deltas[0].function.as_ref().unwrap().arguments.as_deref(),
+                    Some("{\"a\":1}")
+                );
+            }
+            other => panic!("unexpected delta: {:?}", other),
+        }
+        assert_eq!(chunks[2].finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(chunks[2].usage.unwrap().total_tokens, 15);
+    }
+
+    #[test]
+    fn test_unknown_stream_events_are_skipped() {
+        let mut state = AnthropicStreamState::default();
+        let event = SseEvent {
+            event: Some("citation_added".to_string()),
+            data: r#"{"type":"citation_added","index":0}"#.to_string(),
+            id: None,
+        };
+        assert!(AnthropicProvider::process_event(event, &mut state)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_build_request_maps_and_rejects_sampling_params() {
+        let mut request = CompletionRequest {
+            model: "claude-3-5-haiku-latest".to_string(),
+            messages: vec![ChatMessage::user("Hi".to_string())],
+            top_k: Some(40),
+            stop: Some(vec!["END".to_string()]),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(AnthropicProvider::build_request(&request, false).unwrap())
+            .unwrap();
+        assert_eq!(body["top_k"], 40);
+        assert_eq!(body["stop_sequences"], serde_json::json!(["END"]));
+
+        request.seed = Some(7);
+        request.frequency_penalty = Some(0.5);
+        match AnthropicProvider::build_request(&request, false) {
+            Err(ProviderError::Unsupported(message)) => {
+                assert!(message.contains("seed, frequency_penalty"))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_map_messages_image_blocks() {
+        let messages = vec![ChatMessage::user_parts(vec![
+            ContentPart::image_url("data:image/jpeg;base64,/9j/4AAQ"),
+            ContentPart::image_url("https://example.com/cat.png"),
+            ContentPart::text("What is in these images?"),
+        ])];
+        let (_, mapped) = AnthropicProvider::map_messages(&messages).unwrap();
+        let value = serde_json::to_value(&mapped[0].content).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([
+                { "type": "image", "source": { "type": "base64", "media_type": "image/jpeg", "data": "/9j/4AAQ" } },
+                { "type": "image", "source": { "type": "url", "url": "https://example.com/cat.png" } },
+                { "type": "text", "text": "What is in these images?" },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_map_stop_reason() {
+        let map =
+            |reason: &str| AnthropicProvider::map_stop_reason(Some(reason.to_string())).unwrap();
+        assert_eq!(map("end_turn"), FinishReason::Stop);
+        assert_eq!(map("stop_sequence"), FinishReason::StopSequence);
+        assert_eq!(map("max_tokens"), FinishReason::Length);
+        assert_eq!(map("refusal"), FinishReason::ContentFilter);
+        assert_eq!(
+            map("pause_turn"),
+            FinishReason::Other("pause_turn".to_string())
+        );
+        assert_eq!(serde_json::to_value(map("tool_use")).unwrap(), "tool_calls");
+    }
+
+    #[test]
+    fn test_thinking_request_and_response() {
+        let request = CompletionRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![ChatMessage::user("Hi".to_string())],
+            reasoning: Some(Reasoning::Effort(ReasoningEffort::Low)),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(AnthropicProvider::build_request(&request, false).unwrap())
+            .unwrap();
+        assert_eq!(
+            body["thinking"],
+            json!({ "type": "enabled", "budget_tokens": THINKING_BUDGET_LOW })
+        );
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS + THINKING_BUDGET_LOW);
+
+        // An explicit limit must leave room beyond the thinking budget.
+        let limited = CompletionRequest {
+            max_tokens: Some(THINKING_BUDGET_LOW),
+            ..request.clone()
+        };
+        let result = AnthropicProvider::build_request(&limited, false);
+        assert!(
+            matches!(result, Err(ProviderError::Unsupported(message)) if message.contains("max_tokens"))
+        );
+        let limited = CompletionRequest {
+            max_tokens: Some(THINKING_BUDGET_LOW + 1),
+            ..request.clone()
+        };
+        assert!(AnthropicProvider::build_request(&limited, false).is_ok());
+
+        let response: AnthropicMessagesResponse = serde_json::from_value(json!({
+            "content": [
+                { "type": "thinking", "thinking": "The user greets me.", "signature": "abc" },
+                { "type": "text", "text": "Hello!" }
+            ],
+            "stop_reason": "end_turn"
+        }))
+        .unwrap();
+        let choice = AnthropicProvider::map_choice(response.content, None);
+        assert_eq!(choice.reasoning.as_deref(), Some("The user greets me."));
+        assert_eq!(choice.content.as_deref(), Some("Hello!"));
+    }
+
+    #[test]
+    fn test_thinking_blocks_round_trip_into_history() {
+        let response: AnthropicMessagesResponse = serde_json::from_value(json!({
+            "content": [
+                { "type": "thinking", "thinking": "Need the weather.", "signature": "sig-1" },
+                { "type": "redacted_thinking", "data": "EncryptedBlob" },
+                { "type": "tool_use", "id": "toolu_1", "name": "weather", "input": { "city": "Oslo" } }
+            ],
+            "stop_reason": "tool_use"
+        }))
+        .unwrap();
+        let choice = AnthropicProvider::map_choice(response.content, None);
+        let messages = vec![
+            ChatMessage::user("Weather in Oslo?".to_string()),
+            choice.to_message(),
+            ChatMessage::tool_result("toolu_1".to_string(), "Snow".to_string()),
+        ];
+
+        let (_, mapped) = AnthropicProvider::map_messages(&messages).unwrap();
+        let assistant = serde_json::to_value(&mapped[1].content).unwrap();
+        assert_eq!(
+            assistant[0],
+            json!({ "type": "thinking", "thinking": "Need the weather.", "signature": "sig-1" })
+        );
+        assert_eq!(
+            assistant[1],
+            json!({ "type": "redacted_thinking", "data": "EncryptedBlob" })
+        );
+        assert_eq!(assistant[2]["type"], "tool_use");
+    }
+
+    #[test]
+    fn test_stream_emits_signed_thinking_block() {
+        let mut state = AnthropicStreamState::default();
+        let events = [
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "thinking", "thinking": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "thinking_delta", "thinking": "Hmm." } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "signature_delta", "signature": "sig-2" } }),
+            json!({ "type": "content_block_stop", "index": 0 }),
+        ];
+        let chunks: Vec<CompletionStreamChunk> = events
+            .into_iter()
+            .flat_map(|event| {
+                let event: AnthropicStreamEvent = serde_json::from_value(event).unwrap();
+                AnthropicProvider::map_stream_event(event, &mut state).unwrap()
+            })
+            .collect();
+
+        assert_eq!(chunks.len(), 2);
+        assert!(matches!(&chunks[0].delta, StreamContentDelta::Reasoning(t) if t == "Hmm."));
+        let expected = ReasoningBlock::Thinking {
+            thinking: "Hmm.".to_string(),
+            signature: Some("sig-2".to_string()),
+        };
+        assert!(
+            matches!(&chunks[1].delta, StreamContentDelta::ReasoningBlock(block) if *block == expected)
+        );
+    }
+
+    #[test]
+    fn test_thinking_rejects_incompatible_settings() {
+        let parameters = JsonSchema {
+            schema_type: "object".to_string(),
+            properties: None,
+            required: None,
+        };
+        let tool = Tool {
+            name: "weather".to_string(),
+            description: "Weather".to_string(),
+            parameters,
+        };
+        let mut request = CompletionRequest {
+            model: "claude-sonnet-4-5".to_string(),
+            messages: vec![ChatMessage::user("Hi".to_string())],
+            reasoning: Some(Reasoning::BudgetTokens(2048)),
+            temperature: Some(1.0),
+            tools: Some(vec![tool]),
+            tool_choice: Some(ToolChoice::Auto),
+            ..Default::default()
+        };
+        assert!(AnthropicProvider::build_request(&request, false).is_ok());
+
+        request.temperature = Some(0.2);
+        request.top_k = Some(40);
+        request.tool_choice = Some(ToolChoice::Required);
+        match AnthropicProvider::build_request(&request, false) {
+            Err(ProviderError::Unsupported(message)) => {
+                assert!(
+                    message.contains("temperature, top_k, a forced tool_choice"),
+                    "{}",
+                    message
+                )
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        request.reasoning = Some(Reasoning::Disabled);
+        assert!(AnthropicProvider::build_request(&request, false).is_ok());
+    }
+}
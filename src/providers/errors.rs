@@ -0,0 +1,210 @@
+//!
+//! API Error Responses
+//!
+//! Turns non-success HTTP responses into classified `ProviderError::ApiError`s, keeping the
+//! provider's error fields, request id, requested retry delay and raw body.
+
+use crate::providers::retry::server_delay;
+use crate::traits::{ApiErrorDetails, ProviderError};
+use std::time::SystemTime;
+
+/// Error fields a provider parsed out of its error body.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedApiError {
+    pub message: String,
+    pub code: Option<String>,
+    pub error_type: Option<String>,
+    pub param: Option<String>,
+}
+
+/// Reads a non-success response into a `ProviderError::ApiError`.
+///
+/// `parse` extracts the provider's error fields from the body; when it returns `None`,
+/// the whole body is used as the message.
+pub(crate) async fn read_api_error(
+    res: reqwest::Response,
+    parse: impl FnOnce(&str) -> Option<ParsedApiError>,
+) -> ProviderError {
+    let status = res.status().as_u16();
+    let headers = res.headers();
+    let request_id = ["x-request-id", "request-id"]
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok())
+        .map(str::to_string);
+    let retry_after = server_delay(status, headers, SystemTime::now());
+
+    let body = res
+        .text()
+        .await
+        .unwrap_or_else(|_| "Failed to read error body".to_string());
+    let parsed = parse(&body).unwrap_or_else(|| ParsedApiError {
+        message: body.clone(),
+        ..Default::default()
+    });
+
+    let details = ApiErrorDetails {
+        code: parsed.code,
+        error_type: parsed.error_type,
+        param: parsed.param,
+        request_id,
+        retry_after,
+        body: Some(body),
+    };
+    ProviderError::api_error(status, parsed.message, details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::ErrorKind;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serves one raw HTTP response on a local socket and returns it as a `reqwest::Response`.
+    async fn respond(raw: String) -> reqwest::Response {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket.write_all(raw.as_bytes()).await.unwrap();
+        });
+        reqwest::get(&url).await.unwrap()
+    }
+
+    fn http(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn test_keeps_request_id_retry_after_and_body() {
+        let body = r#"{"error":{"message":"Rate limit reached","type":"requests","code":"rate_limit_exceeded"}}"#;
+        let res = respond(http(
+            "429 Too Many Requests",
+            "x-request-id: req_123\r\nretry-after: 7\r\n",
+            body,
+        ))
+        .await;
+
+        let error = read_api_error(res, |body| {
+            let json: serde_json::Value = serde_json::from_str(body).ok()?;
+            Some(ParsedApiError {
+                message: json["error"]["message"].as_str()?.to_string(),
+                code: json["error"]["code"].as_str().map(str::to_string),
+                ..Default::default()
+            })
+        })
+        .await;
+
+        assert_eq!(error.kind(), ErrorKind::RateLimited);
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(7)));
+        assert_eq!(error.request_id(), Some("req_123"));
+        assert_eq!(error.body(), Some(body));
+        assert_eq!(
+            error.to_string(),
+            "API response error: 429: Rate limit reached"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unparsed_body_becomes_message() {
+        let res = respond(http("502 Bad Gateway", "", "upstream down")).await;
+        let error = read_api_error(res, |_| None).await;
+        assert!(
+            matches!(&error, ProviderError::ApiError { status: 502, message, .. } if message == "upstream down")
+        );
+        assert_eq!(error.kind(), ErrorKind::Server);
+    }
+
+    #[test]
+    fn test_classifies_common_provider_errors() {
+        let classify = |status, message: &str, code: Option<&str>, error_type: Option<&str>| {
+            let details = ApiErrorDetails {
+                code: code.map(str::to_string),
+                error_type: error_type.map(str::to_string),
+                ..Default::default()
+            };
+            ProviderError::api_error(status, message.to_string(), details).kind()
+        };
+
+        assert_eq!(
+            classify(
+                401,
+                "Incorrect API key provided",
+                Some("invalid_api_key"),
+                None
+            ),
+            ErrorKind::Authentication
+        );
+        assert_eq!(
+            classify(
+                429,
+                "You exceeded your current quota",
+                Some("insufficient_quota"),
+                None
+            ),
+            ErrorKind::Other
+        );
+        assert_eq!(
+            classify(
+                400,
+                "This model's maximum context length is 8192 tokens",
+                Some("context_length_exceeded"),
+                None
+            ),
+            ErrorKind::ContextLengthExceeded
+        );
+        assert_eq!(
+            classify(
+                400,
+                "prompt is too long: 210000 tokens",
+                None,
+                Some("invalid_request_error")
+            ),
+            ErrorKind::ContextLengthExceeded
+        );
+        assert_eq!(
+            classify(400, "flagged", Some("content_filter"), None),
+            ErrorKind::ContentFiltered
+        );
+        assert_eq!(
+            classify(
+                404,
+                "model \"llama9\" not found, try pulling it first",
+                None,
+                None
+            ),
+            ErrorKind::ModelNotFound
+        );
+        assert_eq!(
+            classify(529, "Overloaded", None, Some("overloaded_error")),
+            ErrorKind::Overloaded
+        );
+        assert_eq!(
+            classify(504, "Gateway timeout", None, None),
+            ErrorKind::Timeout
+        );
+        assert_eq!(
+            classify(422, "bad field", None, None),
+            ErrorKind::InvalidRequest
+        );
+        // 409s are transient for the default retry and fallback policies, and so for the router.
+        assert_eq!(
+            classify(409, "Request timed out waiting for a lock", None, None),
+            ErrorKind::Server
+        );
+        assert!(
+            ProviderError::api_error(409, "conflict".to_string(), Default::default())
+                .is_retryable()
+        );
+        assert!(!ProviderError::Unsupported("x".to_string()).is_retryable());
+    }
+}
@@ -1,14 +1,22 @@
 //!
 //! Provider Implementations Module
-//! 
+//!
 //! This module contains the concrete implementations for each supported LLM provider.
 //! Each provider implements the `LlmProvider` trait defined in `crate::traits`.
 
 // Declare provider implementation modules here
-pub mod openai;
+pub mod anthropic;
 pub mod ollama;
-// pub mod anthropic; // Example for future provider
+pub mod openai;
+
+// Shared helpers for provider implementations
+pub(crate) mod errors;
+pub(crate) mod params;
+pub(crate) mod passthrough;
+pub(crate) mod retry;
+pub(crate) mod sse;
 
 // Re-export provider structs for easier access from the library root.
+pub use anthropic::AnthropicProvider;
+pub use ollama::{OllamaProvider, OllamaToolMode};
 pub use openai::OpenAIProvider;
-pub use ollama::OllamaProvider; 
\ No newline at end of file
@@ -1,43 +1,74 @@
 //!
 //! Ollama Provider Implementation
-//! 
+//!
 //! Provides the `OllamaProvider` struct for interacting with local Ollama instances.
-//! Supports non-streaming chat completions and non-streaming tool calls (via JSON mode).
-//! Streaming tool calls are not supported as they require JSON mode, which Ollama disables for streaming.
+//! Tool calls use Ollama's native `tools` support on `/api/chat` by default. For models
+//! without native tool support, `OllamaToolMode::PromptEmulation` describes the tools in
+//! the system prompt and parses the JSON reply instead (non-streaming only).
+//! Streaming responses are newline-delimited JSON and surface native tool calls as
+//! `StreamContentDelta::ToolCallDelta` chunks.
 
 use crate::config::{LlmConfig, Provider};
+use crate::providers::errors::{read_api_error, ParsedApiError};
+use crate::providers::params::{ensure_supported, SamplingParam};
+use crate::providers::passthrough::{apply_extra_headers, merge_extra_body};
+use crate::providers::retry::send_with_retry;
 use crate::traits::{
-    ChatMessage, ChatMessageRole, CompletionKind, CompletionRequest, CompletionResponse, CompletionStream, CompletionStreamChunk, LlmProvider, ProviderError, StreamContentDelta, TokenUsage, Tool, ToolCallFunction, ToolCallRequest
+    ChatMessage, ChatMessageRole, CompletionChoice, CompletionKind, CompletionRequest,
+    CompletionResponse, CompletionStream, CompletionStreamChunk, ContentPart, EmbeddingProvider,
+    EmbeddingRequest, EmbeddingResponse, FinishReason, JsonSchema, LlmProvider, MessageContent,
+    ModelInfo, ProviderError, Reasoning, ResponseFormat, StreamContentDelta, TokenUsage, Tool,
+    ToolCallFunction, ToolCallFunctionStreamDelta, ToolCallRequest, ToolCallStreamDelta,
+    ToolChoice,
 };
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::stream::TryStreamExt;
+use futures::stream::{self, StreamExt, TryStreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::Client;
+use serde::de::Error as DeError;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::time::Duration;
-use serde::de::Error as DeError;
 use serde_json::Value as JsonValue;
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Default base URL for a local Ollama instance.
 const OLLAMA_DEFAULT_BASE_URL: &str = "http://localhost:11434";
 /// Default request timeout in seconds.
 const DEFAULT_TIMEOUT_SECS: u64 = 120;
+/// Maximum number of concurrent `/api/show` requests made by `list_models`.
+const SHOW_CONCURRENCY: usize = 4;
+
+/// How the Ollama provider handles requests that include tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OllamaToolMode {
+    /// Send tools in Ollama's native `tools` field and read structured `message.tool_calls`.
+    #[default]
+    Native,
+    /// Describe the tools in the system prompt and force JSON output, for models
+    /// without native tool support. Not available for streaming, nor together with a
+    /// JSON Schema `response_format`.
+    PromptEmulation,
+}
 
 // Internal structs mapping to Ollama's API
-// We can reuse ChatMessage from traits.rs
 
 #[derive(Serialize, Debug)]
 struct OllamaChatRequest {
     model: String,
-    messages: Vec<ChatMessage>,
+    messages: Vec<OllamaMessage>,
     stream: bool,
+    /// Either `"json"` or a JSON Schema object.
     #[serde(skip_serializing_if = "Option::is_none")]
-    format: Option<String>,
+    format: Option<JsonValue>,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<OllamaTool>>,
+    /// Either a boolean or an effort level (`"low"`, `"medium"`, `"high"`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    think: Option<JsonValue>,
 }
 
 #[derive(Serialize, Debug, Default)] // Default for easier optional creation
@@ -45,71 +76,170 @@ struct OllamaOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    num_predict: Option<u32>, 
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+#[derive(Serialize, Debug)]
+struct OllamaTool {
+    #[serde(rename = "type")]
+    tool_type: String, // Always "function"
+    function: OllamaFunctionDef,
+}
+
+#[derive(Serialize, Debug)]
+struct OllamaFunctionDef {
+    name: String,
+    description: String,
+    parameters: JsonSchema,
+}
+
+/// A message in Ollama's conversation format.
+#[derive(Serialize, Debug)]
+struct OllamaMessage {
+    role: ChatMessageRole,
+    content: String,
+    /// Base64-encoded images attached to the message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    images: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OllamaToolCall>>,
+    /// Name of the tool whose result this `tool` message carries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+// Message returned by /api/chat, streaming or not
+#[derive(Deserialize, Debug, Default)]
+#[allow(dead_code)] // Allow unused fields from API response
+struct OllamaResponseMessage {
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    thinking: String,
+    tool_calls: Option<Vec<OllamaToolCall>>,
 }
 
 // Non-streaming response
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)] // Allow unused fields from API response
 struct OllamaChatResponse {
     model: String,
     created_at: String,
-    message: ChatMessage, // Reuses the ChatMessage struct
+    message: OllamaResponseMessage,
     done: bool,
+    done_reason: Option<FinishReason>,
     // Timing/token info for non-streaming
     total_duration: Option<u64>,
     load_duration: Option<u64>,
     prompt_eval_count: Option<u32>,
     prompt_eval_duration: Option<u64>,
-    eval_count: Option<u32>,      // Completion tokens
+    eval_count: Option<u32>, // Completion tokens
     eval_duration: Option<u64>,
 }
 
 // Streaming response chunk (newline-delimited JSON)
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)] // Allow unused fields from API response
 struct OllamaChatStreamResponse {
     model: String,
     created_at: String,
     message: OllamaStreamMessage,
     done: bool,
-    done_reason: Option<String>,
+    done_reason: Option<FinishReason>,
 
     // Timing/token info ONLY in the final chunk (when done = true)
     total_duration: Option<u64>,
     load_duration: Option<u64>,
     prompt_eval_count: Option<u32>,
     prompt_eval_duration: Option<u64>,
-    eval_count: Option<u32>,      
+    eval_count: Option<u32>,
     eval_duration: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
+#[allow(dead_code)] // Allow unused fields from API response
 struct OllamaStreamMessage {
+    #[serde(default)]
     role: String,
+    #[serde(default)]
     content: String, // This is the delta content for the stream
+    #[serde(default)]
+    thinking: String,
+    // Native tool calls arrive whole, usually in a single chunk.
+    tool_calls: Option<Vec<OllamaToolCall>>,
+}
+
+// --- Embedding Structures ---
+
+#[derive(Serialize, Debug)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
 }
 
-// Represents the *entire* JSON object returned when format=json
 #[derive(Deserialize, Debug)]
-struct OllamaJsonResponse {
-    model: String,
-    created_at: String,
-    done: bool,
-    total_duration: Option<u64>,
-    load_duration: Option<u64>,
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
     prompt_eval_count: Option<u32>,
-    prompt_eval_duration: Option<u64>,
-    eval_count: Option<u32>,
-    eval_duration: Option<u64>,
-    message: Option<ChatMessage>,
-    tool_calls: Option<Vec<OllamaToolCall>>,
 }
 
-// Standard non-streaming, non-json response
+// --- Model Listing Structures ---
+
 #[derive(Deserialize, Debug)]
-struct OllamaStandardResponse {
+struct OllamaTagsResponse {
+    models: Vec<OllamaTag>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaTag {
+    name: String,
+}
+
+#[derive(Serialize, Debug)]
+struct OllamaShowRequest<'a> {
+    model: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaShowResponse {
+    /// E.g. `["completion", "tools", "vision"]`; missing on older Ollama versions.
+    capabilities: Option<Vec<String>>,
+    /// Architecture-prefixed metadata, e.g. `"llama.context_length"`.
+    #[serde(default)]
+    model_info: HashMap<String, JsonValue>,
+}
+
+/// Per-stream state used while decoding Ollama's NDJSON stream.
+#[derive(Debug, Default)]
+struct OllamaStreamState {
+    /// Bytes of a line not yet terminated by `\n`.
+    buffer: Vec<u8>,
+    /// Number of tool calls emitted so far, used as the next tool call index.
+    tool_calls_emitted: usize,
+}
+
+// Represents the *entire* JSON object returned when format=json (prompt emulation)
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)] // Allow unused fields from API response
+struct OllamaJsonResponse {
     model: String,
     created_at: String,
-    message: ChatMessage,
     done: bool,
     total_duration: Option<u64>,
     load_duration: Option<u64>,
@@ -117,22 +247,26 @@ struct OllamaStandardResponse {
     prompt_eval_duration: Option<u64>,
     eval_count: Option<u32>,
     eval_duration: Option<u64>,
+    message: Option<OllamaResponseMessage>,
+    tool_calls: Option<Vec<OllamaToolCall>>,
 }
 
 // Define the structure we expect the model to put *inside* the message content
 // Or potentially be the *entire* response in JSON mode
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct OllamaToolCallPayload {
-    tool_calls: Vec<OllamaToolCall>, 
+    tool_calls: Vec<OllamaToolCall>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct OllamaToolCall {
-    id: String,
+    // Native tool calls usually come without an id; one is generated when missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
     function: OllamaToolFunction,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct OllamaToolFunction {
     name: String,
     arguments: JsonValue, // Expect arguments as a JSON Value (object), not pre-stringified
@@ -142,17 +276,25 @@ struct OllamaToolFunction {
 
 /// Provides interaction with Ollama instances.
 ///
-/// Supports non-streaming chat completion and non-streaming tool calls (using JSON mode).
+/// Supports chat completion and tool calls, natively (streaming and non-streaming)
+/// or via prompt emulation (non-streaming only).
 #[derive(Debug, Clone)]
 pub struct OllamaProvider {
     config: LlmConfig,
     client: Client,
     base_url: String,
+    tool_mode: OllamaToolMode,
+    /// Send `ReasoningEffort` as a `think` level instead of `true`.
+    think_levels: bool,
 }
 
 impl OllamaProvider {
     /// Creates a new Ollama provider instance.
-    pub fn new(config: LlmConfig) -> Self {
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::ConfigError` if the HTTP client fails to build.
+    pub fn try_new(config: LlmConfig) -> Result<Self, ProviderError> {
         let base_url = config
             .base_url
             .clone()
@@ -161,34 +303,212 @@ impl OllamaProvider {
         let client = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
             .build()
-            .expect("Failed to build Reqwest client");
+            .map_err(|e| {
+                ProviderError::ConfigError(format!("Failed to build HTTP client: {}", e))
+            })?;
 
         // Note: Ollama doesn't typically use an API key, but config validation
         // might check for base_url presence.
-        Self { config, client, base_url }
+        Ok(Self {
+            config,
+            client,
+            base_url,
+            tool_mode: OllamaToolMode::default(),
+            think_levels: false,
+        })
+    }
+
+    /// Creates a new Ollama provider instance.
+    /// Panics if the HTTP client fails to build; use `try_new` to handle the error instead.
+    pub fn new(config: LlmConfig) -> Self {
+        Self::try_new(config).unwrap_or_else(|e| panic!("Failed to create Ollama provider: {}", e))
+    }
+
+    /// Sets how requests with tools are handled (builder style).
+    pub fn with_tool_mode(mut self, tool_mode: OllamaToolMode) -> Self {
+        self.tool_mode = tool_mode;
+        self
+    }
+
+    /// Sends `Reasoning::Effort` as Ollama's `think` level (`"low"`, `"medium"`, `"high"`)
+    /// instead of `true` (builder style).
+    ///
+    /// Only some models accept levels (e.g. gpt-oss); other thinking models reject them,
+    /// so this is off by default.
+    pub fn with_think_levels(mut self, think_levels: bool) -> Self {
+        self.think_levels = think_levels;
+        self
     }
 
-    /// Builds standard HTTP headers for Ollama requests.
-    fn build_headers(&self) -> HeaderMap {
+    /// Builds standard HTTP headers for Ollama requests, plus the request's extra headers.
+    fn build_headers(
+        &self,
+        extra_headers: Option<&HashMap<String, String>>,
+    ) -> Result<HeaderMap, ProviderError> {
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         // No Authorization header needed for default Ollama
-        headers
+        apply_extra_headers(&mut headers, extra_headers)?;
+        Ok(headers)
     }
 
     /// Creates the Ollama options structure from the generic request.
-    fn create_ollama_options(request: &CompletionRequest) -> Option<OllamaOptions> {
+    /// Ollama has no logit bias and always returns a single choice.
+    fn create_ollama_options(
+        request: &CompletionRequest,
+    ) -> Result<Option<OllamaOptions>, ProviderError> {
+        ensure_supported(
+            "Ollama",
+            request,
+            &[SamplingParam::LogitBias, SamplingParam::MultipleChoices],
+        )?;
+
         let options = OllamaOptions {
             temperature: request.temperature,
             num_predict: request.max_tokens,
-            // Map other generic options to Ollama options here
+            top_p: request.top_p,
+            top_k: request.top_k,
+            stop: request.stop.clone(),
+            seed: request.seed,
+            presence_penalty: request.presence_penalty,
+            frequency_penalty: request.frequency_penalty,
         };
         // Only return Some if at least one option is set
-        if options.temperature.is_some() || options.num_predict.is_some() {
-            Some(options)
-        } else {
-            None
+        let is_empty = options.temperature.is_none()
+            && options.num_predict.is_none()
+            && options.top_p.is_none()
+            && options.top_k.is_none()
+            && options.stop.is_none()
+            && options.seed.is_none()
+            && options.presence_penalty.is_none()
+            && options.frequency_penalty.is_none();
+        Ok(if is_empty { None } else { Some(options) })
+    }
+
+    /// Maps the generic response format to Ollama's `format` field,
+    /// which accepts either `"json"` or a JSON Schema object.
+    fn map_response_format(response_format: Option<&ResponseFormat>) -> Option<JsonValue> {
+        match response_format? {
+            ResponseFormat::Text => None,
+            ResponseFormat::JsonObject => Some(JsonValue::String("json".to_string())),
+            ResponseFormat::JsonSchema { schema, .. } => Some(schema.clone()),
+        }
+    }
+
+    /// Maps the generic Tool structure to Ollama's native format.
+    fn map_tools_to_ollama(tools: Option<&Vec<Tool>>) -> Option<Vec<OllamaTool>> {
+        tools.filter(|ts| !ts.is_empty()).map(|ts| {
+            ts.iter()
+                .map(|tool| OllamaTool {
+                    tool_type: "function".to_string(),
+                    function: OllamaFunctionDef {
+                        name: tool.name.clone(),
+                        description: tool.description.clone(),
+                        parameters: tool.parameters.clone(),
+                    },
+                })
+                .collect()
+        })
+    }
+
+    /// Parses a JSON-string tool call argument into the object Ollama expects.
+    fn parse_arguments(call: &ToolCallRequest) -> Result<JsonValue, ProviderError> {
+        if call.function.arguments.trim().is_empty() {
+            return Ok(JsonValue::Object(serde_json::Map::new()));
         }
+        serde_json::from_str(&call.function.arguments).map_err(|e| {
+            ProviderError::ToolFormatError(format!(
+                "Arguments for tool call '{}' are not valid JSON: {}",
+                call.id, e
+            ))
+        })
+    }
+
+    /// Maps the generic conversation into Ollama messages.
+    ///
+    /// With native tools, assistant tool calls are sent as `tool_calls` and tool results
+    /// carry the `tool_name` of the call they answer. With prompt emulation, assistant
+    /// tool calls are replayed as the JSON payload the model was asked to produce.
+    fn map_messages(
+        messages: &[ChatMessage],
+        tool_mode: OllamaToolMode,
+    ) -> Result<Vec<OllamaMessage>, ProviderError> {
+        // Tool results only carry the call id; look up which tool that call used.
+        let tool_names: HashMap<&str, &str> = messages
+            .iter()
+            .flat_map(|m| m.tool_calls.iter().flatten())
+            .map(|call| (call.id.as_str(), call.function.name.as_str()))
+            .collect();
+
+        messages
+            .iter()
+            .map(|msg| {
+                let mut content = msg.text().unwrap_or_default();
+                let images = Self::map_images(msg)?;
+                let mut tool_calls = None;
+
+                if let Some(calls) = msg.tool_calls.as_ref().filter(|c| !c.is_empty()) {
+                    let ollama_calls = calls
+                        .iter()
+                        .map(|call| {
+                            Ok(OllamaToolCall {
+                                id: Some(call.id.clone()),
+                                function: OllamaToolFunction {
+                                    name: call.function.name.clone(),
+                                    arguments: Self::parse_arguments(call)?,
+                                },
+                            })
+                        })
+                        .collect::<Result<Vec<_>, ProviderError>>()?;
+
+                    match tool_mode {
+                        OllamaToolMode::Native => tool_calls = Some(ollama_calls),
+                        OllamaToolMode::PromptEmulation => {
+                            content = serde_json::to_string(&OllamaToolCallPayload {
+                                tool_calls: ollama_calls,
+                            })?;
+                        }
+                    }
+                }
+
+                let tool_name = msg
+                    .tool_call_id
+                    .as_deref()
+                    .and_then(|id| tool_names.get(id))
+                    .map(|name| name.to_string());
+
+                Ok(OllamaMessage {
+                    role: msg.role.clone(),
+                    content,
+                    images,
+                    tool_calls,
+                    tool_name,
+                })
+            })
+            .collect()
+    }
+
+    /// Collects the message's image parts for Ollama's `images` array.
+    /// Ollama only accepts inline base64 data, so remote image URLs are rejected.
+    fn map_images(message: &ChatMessage) -> Result<Option<Vec<String>>, ProviderError> {
+        let Some(MessageContent::Parts(parts)) = &message.content else {
+            return Ok(None);
+        };
+        let images = parts
+            .iter()
+            .filter(|part| !matches!(part, ContentPart::Text { .. }))
+            .map(|part| match part.base64_image() {
+                Some((_, data)) => Ok(data.to_string()),
+                None => Err(ProviderError::Unsupported(
+                    "Ollama only accepts base64 images; image URLs must be downloaded and sent as base64 data".to_string(),
+                )),
+            })
+            .collect::<Result<Vec<_>, ProviderError>>()?;
+        Ok(if images.is_empty() {
+            None
+        } else {
+            Some(images)
+        })
     }
 
     /// Formats tool definitions into a string suitable for inclusion in a system prompt.
@@ -205,8 +525,117 @@ impl OllamaProvider {
         tool_desc
     }
 
+    /// Appends text to the system prompt, creating one at the start if none exists.
+    fn append_system_prompt(messages: &mut Vec<OllamaMessage>, text: String) {
+        if let Some(system_message) = messages
+            .iter_mut()
+            .find(|m| m.role == ChatMessageRole::System)
+        {
+            system_message.content = format!("{}\n\n{}", system_message.content, text);
+        } else {
+            messages.insert(
+                0,
+                OllamaMessage {
+                    role: ChatMessageRole::System,
+                    content: text,
+                    images: None,
+                    tool_calls: None,
+                    tool_name: None,
+                },
+            );
+        }
+    }
+
+    /// Selects the tools to send for the request's tool choice.
+    ///
+    /// Ollama has no `tool_choice` parameter, so `None` drops the tools and a named
+    /// function narrows the list to that tool.
+    fn select_tools(request: &CompletionRequest) -> Result<Option<Vec<Tool>>, ProviderError> {
+        let Some(tools) = request.tools.as_ref().filter(|ts| !ts.is_empty()) else {
+            return match &request.tool_choice {
+                Some(ToolChoice::Required) | Some(ToolChoice::Function { .. }) => {
+                    Err(ProviderError::ToolFormatError(
+                        "tool_choice requires at least one tool in the request".to_string(),
+                    ))
+                }
+                _ => Ok(None),
+            };
+        };
+        match &request.tool_choice {
+            None | Some(ToolChoice::Auto) | Some(ToolChoice::Required) => Ok(Some(tools.clone())),
+            Some(ToolChoice::None) => Ok(None),
+            Some(ToolChoice::Function { name }) => {
+                let tool = tools.iter().find(|t| &t.name == name).ok_or_else(|| {
+                    ProviderError::ToolFormatError(format!(
+                        "tool_choice names unknown tool '{}'",
+                        name
+                    ))
+                })?;
+                Ok(Some(vec![tool.clone()]))
+            }
+        }
+    }
+
+    /// Emulates `Required` and named-function tool choices with a system prompt instruction.
+    fn tool_choice_instruction(tool_choice: Option<&ToolChoice>) -> Option<String> {
+        match tool_choice? {
+            ToolChoice::Required => {
+                Some("You must respond by calling one of the available tools.".to_string())
+            }
+            ToolChoice::Function { name } => {
+                Some(format!("You must respond by calling the '{}' tool.", name))
+            }
+            ToolChoice::Auto | ToolChoice::None => None,
+        }
+    }
+
+    /// Builds a native `/api/chat` request, applying the tool choice emulation.
+    fn build_native_request(
+        &self,
+        request: &CompletionRequest,
+        tools: Option<Vec<Tool>>,
+        stream: bool,
+    ) -> Result<OllamaChatRequest, ProviderError> {
+        let mut messages = Self::map_messages(&request.messages, OllamaToolMode::Native)?;
+        if tools.is_some() {
+            if let Some(instruction) = Self::tool_choice_instruction(request.tool_choice.as_ref()) {
+                Self::append_system_prompt(&mut messages, instruction);
+            }
+        }
+
+        Ok(OllamaChatRequest {
+            model: request.model.clone(),
+            messages,
+            stream,
+            format: Self::map_response_format(request.response_format.as_ref()),
+            options: Self::create_ollama_options(request)?,
+            tools: Self::map_tools_to_ollama(tools.as_ref()),
+            think: self.map_think(request.reasoning)?,
+        })
+    }
+
+    /// Maps the reasoning controls to Ollama's `think` field. An effort level becomes
+    /// `true` unless `with_think_levels` is set; Ollama has no token budget for thinking.
+    fn map_think(&self, reasoning: Option<Reasoning>) -> Result<Option<JsonValue>, ProviderError> {
+        match reasoning {
+            None => Ok(None),
+            Some(Reasoning::Disabled) => Ok(Some(JsonValue::Bool(false))),
+            Some(Reasoning::Effort(effort)) if self.think_levels => {
+                Ok(Some(JsonValue::String(effort.as_str().to_string())))
+            }
+            Some(Reasoning::Effort(_)) => Ok(Some(JsonValue::Bool(true))),
+            Some(Reasoning::BudgetTokens(_)) => Err(ProviderError::Unsupported(
+                "Ollama does not support a thinking token budget; use an effort level instead"
+                    .to_string(),
+            )),
+        }
+    }
+
     /// Calculates token usage if prompt and completion counts are available.
-    fn calculate_usage(prompt_tokens: Option<u32>, completion_tokens: Option<u32>) -> Option<TokenUsage> {
+    fn calculate_usage(
+        prompt_tokens: Option<u32>,
+        completion_tokens: Option<u32>,
+    ) -> Option<TokenUsage> {
         match (prompt_tokens, completion_tokens) {
             (Some(pt), Some(ct)) => Some(TokenUsage {
                 prompt_tokens: pt,
@@ -217,279 +646,874 @@ impl OllamaProvider {
         }
     }
 
-    /// Maps Ollama-specific tool calls (parsed from JSON) to the generic ToolCallRequest structure.
-    fn map_ollama_tool_calls(ollama_calls: Vec<OllamaToolCall>) -> Vec<ToolCallRequest> {
-        ollama_calls.into_iter().map(|call| {
-            ToolCallRequest {
-                id: call.id,
+    /// Maps Ollama-specific tool calls to the generic ToolCallRequest structure.
+    ///
+    /// Calls Ollama returned without an id are numbered `call_{n}` from `first_index`,
+    /// their position within the response.
+    fn map_ollama_tool_calls(
+        ollama_calls: Vec<OllamaToolCall>,
+        first_index: usize,
+    ) -> Vec<ToolCallRequest> {
+        ollama_calls
+            .into_iter()
+            .enumerate()
+            .map(|(offset, call)| ToolCallRequest {
+                id: call
+                    .id
+                    .unwrap_or_else(|| format!("call_{}", first_index + offset)),
                 tool_type: "function".to_string(),
                 function: ToolCallFunction {
                     name: call.function.name,
                     arguments: match call.function.arguments {
                         JsonValue::String(s) => s,
                         other => serde_json::to_string(&other).unwrap_or_default(),
-                    }
+                    },
                 },
-            }
-        }).collect()
+            })
+            .collect()
     }
-}
 
-#[async_trait]
-impl LlmProvider for OllamaProvider {
-    /// Generates a non-streaming completion, potentially using JSON mode for tool calls.
-    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
-        if self.config.provider != Provider::Ollama && self.config.provider != Provider::Custom {
-             return Err(ProviderError::ConfigError(
-                 "Invalid provider configured for OllamaProvider".to_string(),
-             ));
+    /// Sends a request to `/api/chat` with the extra body fields and headers of `request`,
+    /// mapping non-success statuses to `ProviderError::ApiError`.
+    async fn send_chat_request(
+        &self,
+        ollama_request: &OllamaChatRequest,
+        request: &CompletionRequest,
+    ) -> Result<reqwest::Response, ProviderError> {
+        let url = format!("{}/api/chat", self.base_url);
+        let headers = self.build_headers(request.extra_headers.as_ref())?;
+        let body = merge_extra_body(ollama_request, request.extra_body.as_ref())?;
+
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&body),
+        )
+        .await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
         }
+        Ok(res)
+    }
 
-        let mut original_messages = request.messages.clone();
-        let mut use_json_format = false;
+    /// Fetches a model's details from `/api/show` and maps them to `ModelInfo`.
+    async fn show_model(&self, name: String) -> Result<ModelInfo, ProviderError> {
+        let url = format!("{}/api/show", self.base_url);
+        let headers = self.build_headers(None)?;
 
-        // Modify prompt and set format if tools are present
-        if let Some(tools) = &request.tools {
-            if !tools.is_empty() {
-                use_json_format = true;
-                let tool_prompt = Self::format_tools_for_prompt(tools);
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client
+                .post(&url)
+                .headers(headers)
+                .json(&OllamaShowRequest { model: &name }),
+        )
+        .await?;
 
-                // Find or create a system prompt in the original messages
-                if let Some(system_message) = original_messages.iter_mut().find(|m| m.role == ChatMessageRole::System) {
-                    let existing_content = system_message.content.take().unwrap_or_default();
-                    system_message.content = Some(format!("{}\n\n{}", existing_content, tool_prompt));
-                } else {
-                    // Prepend a new system prompt
-                    original_messages.insert(0, ChatMessage {
-                        role: ChatMessageRole::System,
-                        content: Some(tool_prompt),
-                        tool_calls: None, // System prompts don't have tool calls
-                        tool_call_id: None,
-                    });
-                }
-            }
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
         }
 
-        // Create a sanitized version of messages specifically for the Ollama API request,
-        // removing fields Ollama doesn't expect in the input history.
-        let messages_for_ollama_request: Vec<ChatMessage> = original_messages
-            .into_iter()
-            .map(|mut msg| {
-                // Ollama API doesn't use tool_calls or tool_call_id in the request messages list.
-                // Keep content and role.
-                msg.tool_calls = None;
-                // While Ollama doesn't use tool_call_id either, keeping it doesn't seem to cause errors
-                // based on current Ollama API behavior, but we could clear it too if needed.
-                // msg.tool_call_id = None;
-                msg
+        let show_response: OllamaShowResponse = res.json().await?;
+        Ok(Self::map_model(name, show_response))
+    }
+
+    /// Maps `/api/show` details to the generic `ModelInfo`.
+    fn map_model(name: String, show_response: OllamaShowResponse) -> ModelInfo {
+        let context_window = show_response
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|length| length as u32);
+        let capabilities = show_response.capabilities;
+        let has_capability = |name: &str| {
+            capabilities
+                .as_ref()
+                .map(|caps| caps.iter().any(|c| c == name))
+        };
+
+        ModelInfo {
+            id: name,
+            name: None,
+            context_window,
+            supports_tools: has_capability("tools"),
+            supports_vision: has_capability("vision"),
+            pricing: None,
+        }
+    }
+
+    /// Converts a non-success HTTP response into a `ProviderError::ApiError`,
+    /// extracting Ollama's `{"error": "..."}` message when present.
+    async fn api_error(res: reqwest::Response) -> ProviderError {
+        read_api_error(res, |body| {
+            let mut json = serde_json::from_str::<HashMap<String, String>>(body).ok()?;
+            Some(ParsedApiError {
+                message: json.remove("error")?,
+                ..Default::default()
             })
-            .collect();
+        })
+        .await
+    }
 
+    /// Builds a `CompletionResponse` from a native `/api/chat` response,
+    /// keeping any text the model wrote alongside its tool calls.
+    fn map_chat_response(ollama_response: OllamaChatResponse) -> CompletionResponse {
+        let usage = Self::calculate_usage(
+            ollama_response.prompt_eval_count,
+            ollama_response.eval_count,
+        );
+        let message = ollama_response.message;
+        let tool_calls = Self::map_ollama_tool_calls(message.tool_calls.unwrap_or_default(), 0);
 
-        let ollama_request = OllamaChatRequest {
-            model: request.model.clone(),
-            messages: messages_for_ollama_request, // Use the sanitized messages
-            stream: false,
-            format: if use_json_format { Some("json".to_string()) } else { None },
-            options: Self::create_ollama_options(&request),
+        let finish_reason = if !tool_calls.is_empty() {
+            Some(FinishReason::ToolCalls)
+        } else if ollama_response.done {
+            Some(ollama_response.done_reason.unwrap_or(FinishReason::Stop))
+        } else {
+            None
         };
 
-        let url = format!("{}/api/chat", self.base_url);
-        let headers = self.build_headers();
+        let choice = CompletionChoice {
+            content: Some(message.content).filter(|content| !content.is_empty()),
+            reasoning: Some(message.thinking).filter(|thinking| !thinking.is_empty()),
+            tool_calls,
+            finish_reason,
+            ..Default::default()
+        };
+        CompletionResponse {
+            choices: vec![choice],
+            usage,
+        }
+    }
 
-        let res = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(&ollama_request)
-            .send()
-            .await?;
+    /// Builds a single-choice response for the prompt emulation path, where the reply
+    /// is either tool calls or text.
+    fn emulated_response(
+        kind: CompletionKind,
+        usage: Option<TokenUsage>,
+        finish_reason: Option<FinishReason>,
+    ) -> CompletionResponse {
+        let choice = match kind {
+            CompletionKind::Message { content } => CompletionChoice {
+                content: Some(content),
+                finish_reason,
+                ..Default::default()
+            },
+            CompletionKind::ToolCall { tool_calls } => CompletionChoice {
+                tool_calls,
+                finish_reason,
+                ..Default::default()
+            },
+        };
+        CompletionResponse {
+            choices: vec![choice],
+            usage,
+        }
+    }
 
-        if !res.status().is_success() {
-            let status = res.status().as_u16();
-            let error_body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-            let message = serde_json::from_str::<HashMap<String, String>>(&error_body)
-                .ok()
-                .and_then(|json| json.get("error").cloned())
-                .unwrap_or(error_body);
-            return Err(ProviderError::ApiError { status, message });
+    /// Translates one NDJSON stream object into generic chunks.
+    ///
+    /// Each native tool call becomes a complete `ToolCallDelta` with a generated id and
+    /// the next index. The final (`done`) chunk carries usage and the finish reason.
+    fn map_stream_response(
+        ollama_chunk: OllamaChatStreamResponse,
+        state: &mut OllamaStreamState,
+    ) -> Vec<CompletionStreamChunk> {
+        let mut chunks = Vec::new();
+        let message = ollama_chunk.message;
+
+        if !message.thinking.is_empty() {
+            chunks.push(CompletionStreamChunk {
+                delta: StreamContentDelta::Reasoning(message.thinking),
+                usage: None,
+                finish_reason: None,
+            });
         }
 
-        // Handle response based on whether JSON format was requested
-        if use_json_format {
-            let raw_json_response: JsonValue = res.json().await?;
+        if !message.content.is_empty() {
+            chunks.push(CompletionStreamChunk {
+                delta: StreamContentDelta::Text(message.content),
+                usage: None,
+                finish_reason: None,
+            });
+        }
 
-            // Try to parse the whole thing as our expected structure first
-            match serde_json::from_value::<OllamaJsonResponse>(raw_json_response.clone()) {
-                Ok(ollama_response) => {
-                    let usage = Self::calculate_usage(ollama_response.prompt_eval_count, ollama_response.eval_count);
+        let tool_calls = Self::map_ollama_tool_calls(
+            message.tool_calls.unwrap_or_default(),
+            state.tool_calls_emitted,
+        );
+        if !tool_calls.is_empty() {
+            let deltas = tool_calls
+                .into_iter()
+                .map(|call| {
+                    let index = state.tool_calls_emitted;
+                    state.tool_calls_emitted += 1;
+                    ToolCallStreamDelta {
+                        index,
+                        id: Some(call.id),
+                        function: Some(ToolCallFunctionStreamDelta {
+                            name: Some(call.function.name),
+                            arguments: Some(call.function.arguments),
+                        }),
+                    }
+                })
+                .collect();
+            chunks.push(CompletionStreamChunk {
+                delta: StreamContentDelta::ToolCallDelta(deltas),
+                usage: None,
+                finish_reason: None,
+            });
+        }
 
-                    // Check primary tool_calls field first
-                    if let Some(tool_calls) = ollama_response.tool_calls {
-                        Ok(CompletionResponse {
-                            kind: CompletionKind::ToolCall { tool_calls: Self::map_ollama_tool_calls(tool_calls) },
-                            usage,
-                            finish_reason: if ollama_response.done { Some("tool_calls".to_string()) } else { None },
-                        })
-                    } 
-                    // If no top-level tool_calls, check if the *message content* contains it
-                    else if let Some(message) = ollama_response.message {
-                        if let Some(content_str) = &message.content {
-                             // Attempt to parse the message content as JSON containing tool_calls
-                             match serde_json::from_str::<OllamaToolCallPayload>(content_str) {
-                                 Ok(tool_payload) => {
-                                     // Ensure arguments are strings
-                                      Ok(CompletionResponse {
-                                         kind: CompletionKind::ToolCall { tool_calls: Self::map_ollama_tool_calls(tool_payload.tool_calls) },
-                                         usage,
-                                         finish_reason: if ollama_response.done { Some("tool_calls".to_string()) } else { None },
-                                     })
-                                 }
-                                 Err(_) => {
-                                     // Content wasn't the expected tool call JSON, treat as regular message
-                                     Ok(CompletionResponse {
-                                         kind: CompletionKind::Message { content: content_str.clone() },
-                                         usage,
-                                         finish_reason: if ollama_response.done { Some("stop".to_string()) } else { None },
-                                     })
-                                 }
-                             }
+        if ollama_chunk.done {
+            let usage =
+                Self::calculate_usage(ollama_chunk.prompt_eval_count, ollama_chunk.eval_count);
+            // Ollama reports "stop" even when the turn ended with tool calls.
+            let finish_reason = if state.tool_calls_emitted > 0 {
+                Some(FinishReason::ToolCalls)
+            } else {
+                Some(ollama_chunk.done_reason.unwrap_or(FinishReason::Stop))
+            };
+            match chunks.last_mut() {
+                Some(last) => {
+                    last.usage = usage;
+                    last.finish_reason = finish_reason;
+                }
+                None => chunks.push(CompletionStreamChunk {
+                    delta: StreamContentDelta::Text("".to_string()), // Empty delta for final info
+                    usage,
+                    finish_reason,
+                }),
+            }
+        }
+
+        chunks
+    }
+
+    /// Buffers raw bytes and parses every complete NDJSON line into generic chunks.
+    fn process_bytes(
+        chunk: &[u8],
+        state: &mut OllamaStreamState,
+    ) -> Result<Vec<CompletionStreamChunk>, ProviderError> {
+        state.buffer.extend_from_slice(chunk);
+        let mut chunks = Vec::new();
+
+        while let Some(pos) = state.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = state.buffer.drain(..=pos).collect();
+            chunks.extend(Self::process_line(&line, state)?);
+        }
+        Ok(chunks)
+    }
+
+    /// Parses whatever is left in the buffer once the byte stream ends,
+    /// i.e. a last line sent without a trailing newline.
+    fn finish_bytes(
+        state: &mut OllamaStreamState,
+    ) -> Result<Vec<CompletionStreamChunk>, ProviderError> {
+        let line = std::mem::take(&mut state.buffer);
+        Self::process_line(&line, state)
+    }
+
+    /// Parses one NDJSON line into generic chunks; blank lines yield nothing.
+    fn process_line(
+        line: &[u8],
+        state: &mut OllamaStreamState,
+    ) -> Result<Vec<CompletionStreamChunk>, ProviderError> {
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ollama_chunk = serde_json::from_slice::<OllamaChatStreamResponse>(line)?;
+        Ok(Self::map_stream_response(ollama_chunk, state))
+    }
+
+    /// Emulates tool calling for models without native support by describing the tools
+    /// in the system prompt and parsing the JSON-mode reply.
+    async fn emulated_tool_completion(
+        &self,
+        request: &CompletionRequest,
+        tools: Vec<Tool>,
+    ) -> Result<CompletionResponse, ProviderError> {
+        // The emulation needs `format: "json"` for its own reply shape, leaving no room for a schema.
+        if matches!(
+            request.response_format,
+            Some(ResponseFormat::JsonSchema { .. })
+        ) {
+            return Err(ProviderError::Unsupported(
+                "JSON Schema response formats are not supported with tools in Ollama prompt emulation mode".to_string(),
+            ));
+        }
+        let mut messages = Self::map_messages(&request.messages, OllamaToolMode::PromptEmulation)?;
+        let mut tool_prompt = Self::format_tools_for_prompt(&tools);
+        if let Some(instruction) = Self::tool_choice_instruction(request.tool_choice.as_ref()) {
+            tool_prompt = format!("{}\n{}", tool_prompt, instruction);
+        }
+        Self::append_system_prompt(&mut messages, tool_prompt);
+
+        let ollama_request = OllamaChatRequest {
+            model: request.model.clone(),
+            messages,
+            stream: false,
+            format: Some(JsonValue::String("json".to_string())),
+            options: Self::create_ollama_options(request)?,
+            tools: None,
+            think: self.map_think(request.reasoning)?,
+        };
+
+        let res = self.send_chat_request(&ollama_request, request).await?;
+        let raw_json_response: JsonValue = res.json().await?;
+
+        // Try to parse the whole thing as our expected structure first
+        match serde_json::from_value::<OllamaJsonResponse>(raw_json_response.clone()) {
+            Ok(ollama_response) => {
+                let usage = Self::calculate_usage(
+                    ollama_response.prompt_eval_count,
+                    ollama_response.eval_count,
+                );
+
+                // Check primary tool_calls field first
+                if let Some(tool_calls) = ollama_response.tool_calls {
+                    Ok(Self::emulated_response(
+                        CompletionKind::ToolCall {
+                            tool_calls: Self::map_ollama_tool_calls(tool_calls, 0),
+                        },
+                        usage,
+                        if ollama_response.done {
+                            Some(FinishReason::ToolCalls)
                         } else {
-                             // Message content was null, treat as empty message
-                             Ok(CompletionResponse {
-                                 kind: CompletionKind::Message { content: "".to_string() },
-                                 usage,
-                                 finish_reason: if ollama_response.done { Some("stop".to_string()) } else { None },
-                             })
-                        }
-                    } else {
-                         // JSON response didn't match expected structures
-                         Err(ProviderError::ParseError(serde_json::Error::custom(
-                             "Ollama JSON response did not contain expected 'message' or 'tool_calls' field."
-                         )))
-                    }
+                            None
+                        },
+                    ))
                 }
-                Err(_) => {
-                    // Failed to parse as OllamaJsonResponse, maybe it's just the tool call payload directly?
-                    match serde_json::from_value::<OllamaToolCallPayload>(raw_json_response) {
-                        Ok(tool_payload) => {
-                             // Estimate usage? Difficult without the standard response fields.
-                             let usage = None; 
-                              Ok(CompletionResponse {
-                                 kind: CompletionKind::ToolCall { tool_calls: Self::map_ollama_tool_calls(tool_payload.tool_calls) },
-                                 usage,
-                                 finish_reason: Some("tool_calls".to_string()), // Assume tool call finish
-                             })
-                        }
-                        Err(e) => {
-                            // Couldn't parse as standard response or tool call payload
-                             Err(ProviderError::ParseError(e))
-                        }
+                // If no top-level tool_calls, check if the *message content* contains it
+                else if let Some(message) = ollama_response.message {
+                    // Attempt to parse the message content as JSON containing tool_calls
+                    match serde_json::from_str::<OllamaToolCallPayload>(&message.content) {
+                        Ok(tool_payload) => Ok(Self::emulated_response(
+                            CompletionKind::ToolCall {
+                                tool_calls: Self::map_ollama_tool_calls(tool_payload.tool_calls, 0),
+                            },
+                            usage,
+                            if ollama_response.done {
+                                Some(FinishReason::ToolCalls)
+                            } else {
+                                None
+                            },
+                        )),
+                        // Content wasn't the expected tool call JSON, treat as regular message
+                        Err(_) => Ok(Self::emulated_response(
+                            CompletionKind::Message {
+                                content: message.content,
+                            },
+                            usage,
+                            if ollama_response.done {
+                                Some(FinishReason::Stop)
+                            } else {
+                                None
+                            },
+                        )),
                     }
+                } else {
+                    // JSON response didn't match expected structures
+                    Err(ProviderError::ParseError(serde_json::Error::custom(
+                         "Ollama JSON response did not contain expected 'message' or 'tool_calls' field."
+                     )))
+                }
+            }
+            Err(_) => {
+                // Failed to parse as OllamaJsonResponse, maybe it's just the tool call payload directly?
+                match serde_json::from_value::<OllamaToolCallPayload>(raw_json_response) {
+                    Ok(tool_payload) => Ok(Self::emulated_response(
+                        CompletionKind::ToolCall {
+                            tool_calls: Self::map_ollama_tool_calls(tool_payload.tool_calls, 0),
+                        },
+                        None, // Not available without the standard response fields
+                        Some(FinishReason::ToolCalls), // Assume tool call finish
+                    )),
+                    // Couldn't parse as standard response or tool call payload
+                    Err(e) => Err(ProviderError::ParseError(e)),
                 }
             }
-        } else {
-            // Standard non-JSON response parsing
-            let ollama_response: OllamaStandardResponse = res.json().await?;
-            let usage = Self::calculate_usage(ollama_response.prompt_eval_count, ollama_response.eval_count);
-            Ok(CompletionResponse {
-                kind: CompletionKind::Message { content: ollama_response.message.content.unwrap_or_default() },
-                usage,
-                finish_reason: if ollama_response.done { Some("stop".to_string()) } else { None },
-            })
         }
     }
+}
 
-    /// Generates a streaming completion (tool calls unsupported).
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    /// Generates a non-streaming completion, using native tool calling unless
+    /// prompt emulation was selected.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        if self.config.provider != Provider::Ollama && self.config.provider != Provider::Custom {
+            return Err(ProviderError::ConfigError(
+                "Invalid provider configured for OllamaProvider".to_string(),
+            ));
+        }
+
+        let tools = Self::select_tools(&request)?;
+        if let Some(tools) = tools.clone() {
+            if self.tool_mode == OllamaToolMode::PromptEmulation {
+                return self.emulated_tool_completion(&request, tools).await;
+            }
+        }
+
+        let ollama_request = self.build_native_request(&request, tools, false)?;
+
+        let res = self.send_chat_request(&ollama_request, &request).await?;
+        let ollama_response: OllamaChatResponse = res.json().await?;
+        Ok(Self::map_chat_response(ollama_response))
+    }
+
+    /// Generates a streaming completion, surfacing native tool calls as tool call deltas.
     async fn completion_stream(
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionStream, ProviderError> {
-        // Keep tool check for streaming because format=json disables it
-        if request.tools.is_some() {
+        let tools = Self::select_tools(&request)?;
+        if tools.is_some() && self.tool_mode == OllamaToolMode::PromptEmulation {
             return Err(ProviderError::Unsupported(
-                "Streaming tool calls are not currently supported by the Ollama provider (requires format=json which disables streaming).".to_string()
+                "Streaming tool calls are not supported in Ollama prompt emulation mode (requires format=json).".to_string()
             ));
         }
 
         if self.config.provider != Provider::Ollama && self.config.provider != Provider::Custom {
-             return Err(ProviderError::ConfigError(
-                 "Invalid provider configured for OllamaProvider".to_string(),
-             ));
+            return Err(ProviderError::ConfigError(
+                "Invalid provider configured for OllamaProvider".to_string(),
+            ));
         }
 
-        let ollama_request = OllamaChatRequest {
-            model: request.model.clone(),
-            messages: request.messages.clone(), // Use original messages for streaming
-            stream: true,
-            format: None, // Cannot use JSON format with streaming
-            options: Self::create_ollama_options(&request),
+        let ollama_request = self.build_native_request(&request, tools, true)?;
+
+        let res = self.send_chat_request(&ollama_request, &request).await?;
+
+        // Process the newline-delimited JSON stream; state is owned by the closure.
+        // `None` marks the end of the body, where an unterminated last line is flushed.
+        let byte_stream = res
+            .bytes_stream()
+            .map_ok(Some)
+            .chain(stream::once(async { Ok(None) }))
+            .map_err(ProviderError::RequestError);
+        let mut state = OllamaStreamState::default();
+
+        let chunk_stream = byte_stream
+            .map_ok(move |chunk: Option<Bytes>| {
+                let result = match chunk {
+                    Some(chunk) => Self::process_bytes(&chunk, &mut state),
+                    None => Self::finish_bytes(&mut state),
+                };
+                let items: Vec<Result<CompletionStreamChunk, ProviderError>> = match result {
+                    Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                stream::iter(items)
+            })
+            .try_flatten();
+
+        Ok(Box::pin(chunk_stream))
+    }
+
+    /// Lists local models through `/api/tags`, then fetches each model's context window
+    /// and capabilities from `/api/show`, a few at a time. A model whose details cannot be
+    /// fetched is still listed, with its metadata left empty.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let url = format!("{}/api/tags", self.base_url);
+        let headers = self.build_headers(None)?;
+
+        let res =
+            send_with_retry(&self.config.retry, self.client.get(&url).headers(headers)).await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
+        }
+
+        let tags: OllamaTagsResponse = res.json().await?;
+        let mut models: Vec<(usize, ModelInfo)> = stream::iter(tags.models.into_iter().enumerate())
+            .map(|(position, tag)| async move {
+                let info = match self.show_model(tag.name.clone()).await {
+                    Ok(info) => info,
+                    Err(_) => ModelInfo {
+                        id: tag.name,
+                        ..Default::default()
+                    },
+                };
+                (position, info)
+            })
+            .buffer_unordered(SHOW_CONCURRENCY)
+            .collect()
+            .await;
+        // Keep the order of `/api/tags`.
+        models.sort_by_key(|(position, _)| *position);
+        Ok(models.into_iter().map(|(_, info)| info).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaProvider {
+    /// Computes embeddings through the `/api/embed` endpoint.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        let embed_request = OllamaEmbedRequest {
+            model: &request.model,
+            input: &request.input,
+            dimensions: request.dimensions,
         };
 
-        let url = format!("{}/api/chat", self.base_url);
-        let headers = self.build_headers();
+        let url = format!("{}/api/embed", self.base_url);
+        let headers = self.build_headers(None)?;
 
-        let res = self
-            .client
-            .post(&url)
-            .headers(headers)
-            .json(&ollama_request)
-            .send()
-            .await?;
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&embed_request),
+        )
+        .await?;
 
         if !res.status().is_success() {
-            let status = res.status().as_u16();
-            let error_body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-            let message = serde_json::from_str::<HashMap<String, String>>(&error_body)
-                .ok()
-                .and_then(|json| json.get("error").cloned())
-                .unwrap_or(error_body);
-            return Err(ProviderError::ApiError { status, message });
+            return Err(Self::api_error(res).await);
         }
 
-        // Process the newline-delimited JSON stream
-        let byte_stream = res.bytes_stream().map_err(ProviderError::RequestError);
-
-        let chunk_stream = byte_stream.try_filter_map(|chunk: Bytes| async move {
-            // Need to handle potential partial JSON objects across chunks if lines are split
-            // For simplicity here, we assume each chunk contains whole lines
-            // A more robust solution would buffer incomplete lines
-            let lines = chunk.split(|&b| b == b'\n');
-            let mut result_chunk: Option<CompletionStreamChunk> = None;
-
-            for line in lines {
-                if line.is_empty() { continue; }
-
-                match serde_json::from_slice::<OllamaChatStreamResponse>(line) {
-                    Ok(ollama_chunk) => {
-                        let delta_content = ollama_chunk.message.content;
-                        let usage = Self::calculate_usage(ollama_chunk.prompt_eval_count, ollama_chunk.eval_count);
-                        let finish_reason = ollama_chunk.done_reason;
-
-                         // Send a chunk if there's content or if it's the final chunk
-                         if !delta_content.is_empty() || ollama_chunk.done {
-                             result_chunk = Some(CompletionStreamChunk {
-                                 delta: StreamContentDelta::Text(delta_content),
-                                 usage,
-                                 finish_reason,
-                             });
-                         }
-                    }
-                    Err(e) => {
-                        // Log error and potentially yield an error
-                        eprintln!(
-                            "Failed to parse Ollama stream chunk: {:?}, data: {}",
-                            e,
-                            String::from_utf8_lossy(line)
-                        );
-                        // If it's a parsing error, maybe we should stop the stream
-                         return Err(ProviderError::ParseError(e));
-                    }
-                }
+        let ollama_response: OllamaEmbedResponse = res.json().await?;
+        Ok(EmbeddingResponse {
+            embeddings: ollama_response.embeddings,
+            usage: Self::calculate_usage(ollama_response.prompt_eval_count, Some(0)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RetryPolicy;
+
+    fn provider() -> OllamaProvider {
+        OllamaProvider::new(LlmConfig::new(Provider::Ollama))
+    }
+
+    #[test]
+    fn test_map_messages_native_tool_history() {
+        let call = ToolCallRequest::new_function_call(
+            "call_7".to_string(),
+            ToolCallFunction {
+                name: "get_weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+            },
+        );
+        let messages = vec![
+            ChatMessage::user("Weather in Paris?".to_string()),
+            ChatMessage::assistant(None, Some(vec![call])),
+            ChatMessage::tool_result("call_7".to_string(), "Sunny".to_string()),
+        ];
+
+        let mapped = OllamaProvider::map_messages(&messages, OllamaToolMode::Native).unwrap();
+        let body = serde_json::to_value(&mapped).unwrap();
+        assert_eq!(
+            body[1]["tool_calls"][0]["function"]["arguments"]["city"],
+            "Paris"
+        );
+        assert_eq!(body[1]["content"], "");
+        assert_eq!(body[2]["role"], "tool");
+        assert_eq!(body[2]["tool_name"], "get_weather");
+        assert_eq!(body[2]["content"], "Sunny");
+    }
+
+    #[test]
+    fn test_native_tool_call_response() {
+        let response: OllamaChatResponse = serde_json::from_value(serde_json::json!({
+            "model": "qwen3:4b",
+            "created_at": "2025-01-01T00:00:00Z",
+            "message": {
+                "role": "assistant",
+                "content": "Let me check the weather.",
+                "tool_calls": [{"function": {"name": "get_weather", "arguments": {"city": "Paris"}}}]
+            },
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 12,
+            "eval_count": 8
+        }))
+        .unwrap();
+
+        let mapped = OllamaProvider::map_chat_response(response);
+        assert_eq!(mapped.finish_reason(), Some(&FinishReason::ToolCalls));
+        assert_eq!(mapped.usage.unwrap().total_tokens, 20);
+        assert_eq!(mapped.content(), Some("Let me check the weather."));
+        let tool_calls = mapped.tool_calls();
+        assert_eq!(tool_calls[0].function.name, "get_weather");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(tool_calls[0].id, "call_0");
+    }
+
+    #[test]
+    fn test_stream_tool_calls_across_reads() {
+        let mut state = OllamaStreamState::default();
+        let lines = concat!(
+            "{\"model\":\"m\",\"created_at\":\"t\",\"message\":{\"role\":\"assistant\",\"content\":\"\",\"tool_calls\":[",
+            "{\"function\":{\"name\":\"add\",\"arguments\":{\"a\":1}}},{\"function\":{\"name\":\"mul\",\"arguments\":{}}}]},\"done\":false}\n",
+            "{\"model\":\"m\",\"created_at\":\"t\",\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\",\"prompt_eval_count\":3,\"eval_count\":4}",
+        );
+        let (first, second) = lines.split_at(60);
+
+        let mut chunks = OllamaProvider::process_bytes(first.as_bytes(), &mut state).unwrap();
+        assert!(chunks.is_empty());
+        chunks.extend(OllamaProvider::process_bytes(second.as_bytes(), &mut state).unwrap());
+        assert_eq!(chunks.len(), 1);
+        // The last line has no trailing newline and is only parsed when the body ends.
+        chunks.extend(OllamaProvider::finish_bytes(&mut state).unwrap());
+
+        assert_eq!(chunks.len(), 2);
+        match &chunks[0].delta {
+            StreamContentDelta::ToolCallDelta(deltas) => {
+                assert_eq!(deltas.len(), 2);
+                assert_eq!(deltas[1].index, 1);
+                assert_eq!(
+                    (deltas[0].id.as_deref(), deltas[1].id.as_deref()),
+                    (Some("call_0"), Some("call_1"))
+                );
+                assert_eq!(
+                    deltas[0].function.as_ref().unwrap().arguments.as_deref(),
+                    Some(r#"{"a":1}"#)
+                );
+            }
+            other => panic!("expected tool call delta, got {:?}", other),
+        }
+        assert_eq!(chunks[1].finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(chunks[1].usage.unwrap().total_tokens, 7);
+    }
+
+    #[test]
+    fn test_named_tool_choice_narrows_tools_and_instructs() {
+        let tool = |name: &str| Tool {
+            name: name.to_string(),
+            description: format!("The {} tool", name),
+            parameters: JsonSchema {
+                schema_type: "object".to_string(),
+                properties: None,
+                required: None,
+            },
+        };
+        let request = CompletionRequest {
+            model: "qwen3:4b".to_string(),
+            messages: vec![ChatMessage::user("Extract the city.".to_string())],
+            tools: Some(vec![tool("search"), tool("extract")]),
+            tool_choice: Some(ToolChoice::Function {
+                name: "extract".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let tools = OllamaProvider::select_tools(&request).unwrap();
+        let ollama_request = provider()
+            .build_native_request(&request, tools, false)
+            .unwrap();
+        let body = serde_json::to_value(&ollama_request).unwrap();
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(body["tools"][0]["function"]["name"], "extract");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert!(body["messages"][0]["content"]
+            .as_str()
+            .unwrap()
+            .contains("'extract'"));
+
+        let none = CompletionRequest {
+            tool_choice: Some(ToolChoice::None),
+            ..request
+        };
+        assert!(OllamaProvider::select_tools(&none).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_response_format_maps_to_format_field() {
+        let schema =
+            serde_json::json!({"type": "object", "properties": {"city": {"type": "string"}}});
+        let request = CompletionRequest {
+            model: "qwen3:4b".to_string(),
+            messages: vec![ChatMessage::user("Where is the Eiffel Tower?".to_string())],
+            response_format: Some(ResponseFormat::json_schema("location", schema.clone())),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(
+            provider()
+                .build_native_request(&request, None, false)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["format"], schema);
+
+        let json_mode = CompletionRequest {
+            response_format: Some(ResponseFormat::JsonObject),
+            ..request.clone()
+        };
+        let body = serde_json::to_value(
+            provider()
+                .build_native_request(&json_mode, None, false)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["format"], "json");
+
+        // Prompt emulation already uses the format for its tool call reply, so a schema is refused.
+        let tool = Tool {
+            name: "search".to_string(),
+            description: "Search the web".to_string(),
+            parameters: JsonSchema {
+                schema_type: "object".to_string(),
+                properties: None,
+                required: None,
+            },
+        };
+        let with_tools = CompletionRequest {
+            tools: Some(vec![tool]),
+            ..request
+        };
+        let emulated = provider().with_tool_mode(OllamaToolMode::PromptEmulation);
+        let result = futures::executor::block_on(emulated.completion(with_tools));
+        assert!(
+            matches!(result, Err(ProviderError::Unsupported(message)) if message.contains("JSON Schema"))
+        );
+    }
+
+    #[test]
+    fn test_create_ollama_options_maps_sampling_params() {
+        let mut request = CompletionRequest {
+            model: "llama3".to_string(),
+            top_k: Some(20),
+            stop: Some(vec!["\n\n".to_string()]),
+            seed: Some(42),
+            ..Default::default()
+        };
+        let options =
+            serde_json::to_value(OllamaProvider::create_ollama_options(&request).unwrap()).unwrap();
+        assert_eq!(
+            options,
+            serde_json::json!({ "top_k": 20, "stop": ["\n\n"], "seed": 42 })
+        );
+
+        request.n = Some(2);
+        assert!(matches!(
+            OllamaProvider::create_ollama_options(&request),
+            Err(ProviderError::Unsupported(message)) if message.contains("n > 1")
+        ));
+    }
+
+    #[test]
+    fn test_map_messages_images() {
+        let message = ChatMessage::user_parts(vec![
+            ContentPart::text("What is this?"),
+            ContentPart::image_base64("image/png", "iVBORw0KGgo="),
+        ]);
+        let mapped = OllamaProvider::map_messages(&[message], OllamaToolMode::Native).unwrap();
+        assert_eq!(mapped[0].content, "What is this?");
+        assert_eq!(
+            mapped[0].images.as_deref(),
+            Some(&["iVBORw0KGgo=".to_string()][..])
+        );
+
+        let remote =
+            ChatMessage::user_parts(vec![ContentPart::image_url("https://example.com/cat.png")]);
+        assert!(matches!(
+            OllamaProvider::map_messages(&[remote], OllamaToolMode::Native),
+            Err(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_stream_thinking_and_think_param() {
+        let mut state = OllamaStreamState::default();
+        let chunks = OllamaProvider::process_bytes(
+            b"{\"model\":\"m\",\"created_at\":\"t\",\"message\":{\"role\":\"assistant\",\"content\":\"\",\"thinking\":\"Hmm.\"},\"done\":false}\n",
+            &mut state,
+        )
+        .unwrap();
+        assert!(matches!(&chunks[0].delta, StreamContentDelta::Reasoning(t) if t == "Hmm."));
+
+        let high = Some(Reasoning::Effort(crate::traits::ReasoningEffort::High));
+        assert_eq!(
+            provider().map_think(high).unwrap(),
+            Some(JsonValue::Bool(true))
+        );
+        assert_eq!(
+            provider().with_think_levels(true).map_think(high).unwrap(),
+            Some(JsonValue::from("high"))
+        );
+        assert_eq!(
+            provider().map_think(Some(Reasoning::Disabled)).unwrap(),
+            Some(JsonValue::Bool(false))
+        );
+        assert!(matches!(
+            provider().map_think(Some(Reasoning::BudgetTokens(100))),
+            Err(ProviderError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_model_from_show_response() {
+        let show_response: OllamaShowResponse = serde_json::from_value(serde_json::json!({
+            "capabilities": ["completion", "tools"],
+            "model_info": { "general.architecture": "qwen3", "qwen3.context_length": 40960 }
+        }))
+        .unwrap();
+        let model = OllamaProvider::map_model("qwen3:4b".to_string(), show_response);
+        assert_eq!(model.id, "qwen3:4b");
+        assert_eq!(model.context_window, Some(40960));
+        assert_eq!(model.supports_tools, Some(true));
+        assert_eq!(model.supports_vision, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_list_models_keeps_models_whose_details_fail() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 4096];
+                let n = socket.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let (status, body) = if request.starts_with("GET /api/tags") {
+                    (
+                        "200 OK",
+                        r#"{"models":[{"name":"qwen3:4b"},{"name":"broken:1b"}]}"#,
+                    )
+                } else if request.contains("broken:1b") {
+                    (
+                        "500 Internal Server Error",
+                        r#"{"error":"failed to load model"}"#,
+                    )
+                } else {
+                    (
+                        "200 OK",
+                        r#"{"capabilities":["completion","tools"],"model_info":{"qwen3.context_length":40960}}"#,
+                    )
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
             }
-            Ok(result_chunk) // Return the processed chunk (if any) for this Bytes item
         });
 
-        Ok(Box::pin(chunk_stream))
+        let config = LlmConfig::new(Provider::Ollama)
+            .with_base_url(base_url)
+            .with_retry_policy(RetryPolicy::none());
+        let models = OllamaProvider::new(config).list_models().await.unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(
+            (models[0].id.as_str(), models[0].context_window),
+            ("qwen3:4b", Some(40960))
+        );
+        assert_eq!(
+            models[1],
+            ModelInfo {
+                id: "broken:1b".to_string(),
+                ..Default::default()
+            }
+        );
     }
-} 
\ No newline at end of file
+}
@@ -1,27 +1,33 @@
 //!
 //! OpenAI Provider Implementation
-//! 
-//! This module provides the `OpenAIProvider` struct and its implementation 
+//!
+//! This module provides the `OpenAIProvider` struct and its implementation
 //! of the `LlmProvider` trait for interacting with OpenAI-compatible APIs
 //! (including OpenAI itself and proxies like OpenRouter).
 
 use crate::config::{LlmConfig, Provider, APP_SITE_NAME, APP_SITE_URL};
+use crate::providers::errors::{read_api_error, ParsedApiError};
+use crate::providers::params::{ensure_supported, SamplingParam};
+use crate::providers::passthrough::{apply_extra_headers, merge_extra_body};
+use crate::providers::retry::send_with_retry;
+use crate::providers::sse::{decode_sse_stream, SseEvent};
 use crate::traits::{
-    ChatMessage, CompletionKind, CompletionRequest, CompletionResponse, CompletionStream,
-    CompletionStreamChunk, JsonSchema, LlmProvider, ProviderError, StreamContentDelta, Tool,
-    ToolCallFunction, ToolCallFunctionStreamDelta, ToolCallRequest, ToolCallStreamDelta, TokenUsage,
+    ChatMessage, ChatMessageRole, CompletionChoice, CompletionRequest, CompletionResponse,
+    CompletionStream, CompletionStreamChunk, ContentPart, EmbeddingProvider, EmbeddingRequest,
+    EmbeddingResponse, FinishReason, JsonSchema, LlmProvider, MessageContent, ModelInfo,
+    ModelPricing, ProviderError, Reasoning, ResponseFormat, StreamContentDelta, TokenUsage, Tool,
+    ToolCallFunction, ToolCallFunctionStreamDelta, ToolCallRequest, ToolCallStreamDelta,
+    ToolChoice,
 };
 use async_trait::async_trait;
-use bytes::Bytes;
-use futures::stream::TryStreamExt; // Keep TryStreamExt for stream processing
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use reqwest::Client;
+use serde::de::Error as DeError;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, json, Value as JsonValue};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use serde::de::Error as DeError;
+use zeroize::Zeroizing;
 
 /// Base URL for the official OpenAI API.
 const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
@@ -44,19 +50,77 @@ struct OpenAIFunctionDef {
     parameters: JsonSchema,
 }
 
+#[derive(Serialize, Debug)]
+struct OpenAIRequestMessage {
+    role: ChatMessageRole,
+    content: Option<OpenAIContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCallRequest>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum OpenAIContent {
+    Text(String),
+    Parts(Vec<OpenAIContentPart>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OpenAIContentPart {
+    Text { text: String },
+    ImageUrl { image_url: OpenAIImageUrl },
+}
+
+#[derive(Serialize, Debug)]
+struct OpenAIImageUrl {
+    url: String,
+}
+
 #[derive(Serialize, Debug)]
 struct OpenAIChatRequest {
     model: String,
-    messages: Vec<ChatMessage>,
+    messages: Vec<OpenAIRequestMessage>,
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    /// Replaces `max_tokens` for OpenAI's reasoning models, which reject the latter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logit_bias: Option<HashMap<u32, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,
     stream: bool,
+    /// Asks streams to end with a usage-only chunk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<JsonValue>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<OpenAITool>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<JsonValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<&'static str>,
+    /// OpenRouter's unified reasoning parameter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning: Option<JsonValue>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -70,9 +134,9 @@ struct OpenAIChatResponse {
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)] // Allow unused fields from API response
 struct OpenAIChoice {
-    // index: u32, // Often unused
+    index: Option<u32>,
     message: OpenAIMessage,
-    finish_reason: Option<String>,
+    finish_reason: Option<FinishReason>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -81,6 +145,11 @@ struct OpenAIMessage {
     // role: String, // Often unused
     content: Option<String>,
     tool_calls: Option<Vec<OpenAIToolCall>>,
+    refusal: Option<String>,
+    /// Reasoning text as returned by OpenRouter.
+    reasoning: Option<String>,
+    /// Reasoning text as returned by DeepSeek and compatible servers.
+    reasoning_content: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -110,6 +179,8 @@ struct OpenAIUsage {
 #[allow(dead_code)] // Allow unused fields from API response
 struct OpenAIChatStreamResponse {
     // model: String, // Often unused
+    // The final usage-only chunk has no choices.
+    #[serde(default)]
     choices: Vec<OpenAIStreamChoice>,
     usage: Option<OpenAIUsage>,
 }
@@ -119,7 +190,7 @@ struct OpenAIChatStreamResponse {
 struct OpenAIStreamChoice {
     // index: u32, // Often unused
     delta: OpenAIStreamDelta,
-    finish_reason: Option<String>,
+    finish_reason: Option<FinishReason>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -128,12 +199,15 @@ struct OpenAIStreamDelta {
     // role: Option<String>, // Often unused
     content: Option<String>,
     tool_calls: Option<Vec<OpenAIStreamToolCallDelta>>,
+    reasoning: Option<String>,
+    reasoning_content: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 #[allow(dead_code)] // Allow unused fields from API response
 struct OpenAIStreamToolCallDelta {
-    index: usize,
+    // Some OpenAI-compatible backends omit the index or reuse it for parallel calls.
+    index: Option<usize>,
     id: Option<String>,
     // tool_type: Option<String>, // Often unused
     function: Option<OpenAIStreamFunctionDelta>,
@@ -145,6 +219,121 @@ struct OpenAIStreamFunctionDelta {
     arguments: Option<String>,
 }
 
+/// Tracks tool calls seen so far in a stream so every delta gets a stable index.
+///
+/// OpenAI sends a distinct `index` per parallel call, but some compatible backends
+/// (e.g. via OpenRouter) send every call with index 0 and only vary the `id`,
+/// so new ids always open a new slot.
+#[derive(Debug, Default)]
+struct OpenAIToolCallTracker {
+    /// The id of each tool call, by generic index.
+    ids: Vec<Option<String>>,
+    /// Maps upstream indexes to generic indexes.
+    upstream: HashMap<usize, usize>,
+}
+
+impl OpenAIToolCallTracker {
+    /// Resolves the generic index for an upstream tool call delta.
+    fn resolve(&mut self, upstream_index: Option<usize>, id: Option<&str>) -> usize {
+        if let Some(id) = id {
+            if let Some(pos) = self
+                .ids
+                .iter()
+                .position(|known| known.as_deref() == Some(id))
+            {
+                return pos;
+            }
+            // First delta for the mapped slot may have arrived without an id.
+            if let Some(&pos) = upstream_index.and_then(|i| self.upstream.get(&i)) {
+                if self.ids[pos].is_none() {
+                    self.ids[pos] = Some(id.to_string());
+                    return pos;
+                }
+            }
+            return self.open_slot(upstream_index, Some(id.to_string()));
+        }
+        match upstream_index {
+            Some(i) => match self.upstream.get(&i) {
+                Some(&pos) => pos,
+                None => self.open_slot(Some(i), None),
+            },
+            // No index and no id: continuation of the most recent call.
+            None => match self.ids.len() {
+                0 => self.open_slot(None, None),
+                len => len - 1,
+            },
+        }
+    }
+
+    fn open_slot(&mut self, upstream_index: Option<usize>, id: Option<String>) -> usize {
+        let pos = self.ids.len();
+        self.ids.push(id);
+        if let Some(i) = upstream_index {
+            self.upstream.insert(i, pos);
+        }
+        pos
+    }
+}
+
+// --- Embedding Structures ---
+
+#[derive(Serialize, Debug)]
+struct OpenAIEmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAIEmbeddingResponse {
+    data: Vec<OpenAIEmbedding>,
+    usage: Option<OpenAIEmbeddingUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAIEmbedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAIEmbeddingUsage {
+    prompt_tokens: u32,
+    total_tokens: u32,
+}
+
+// --- Model Listing Structures ---
+
+#[derive(Deserialize, Debug)]
+struct OpenAIModelList {
+    data: Vec<OpenAIModel>,
+}
+
+/// A `/models` entry. OpenRouter adds the optional metadata fields.
+#[derive(Deserialize, Debug)]
+struct OpenAIModel {
+    id: String,
+    name: Option<String>,
+    context_length: Option<u32>,
+    pricing: Option<OpenRouterPricing>,
+    architecture: Option<OpenRouterArchitecture>,
+    supported_parameters: Option<Vec<String>>,
+}
+
+/// OpenRouter prices are decimal strings in USD per token.
+#[derive(Deserialize, Debug)]
+struct OpenRouterPricing {
+    prompt: Option<String>,
+    completion: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenRouterArchitecture {
+    #[serde(default)]
+    input_modalities: Vec<String>,
+}
+
 // For parsing OpenAI's specific error structure
 #[derive(Deserialize, Debug)]
 struct OpenAIErrorResponse {
@@ -153,33 +342,51 @@ struct OpenAIErrorResponse {
 #[derive(Deserialize, Debug)]
 struct OpenAIErrorDetail {
     message: String,
-    // code: Option<String>,
-    // param: Option<String>,
-    // error_type: Option<String>, // Renamed from type
+    /// A string like `context_length_exceeded`; OpenRouter sends the HTTP status as a number.
+    #[serde(default)]
+    code: Option<JsonValue>,
+    #[serde(default)]
+    param: Option<String>,
+    #[serde(rename = "type", default)]
+    error_type: Option<String>,
 }
 
 // --- Provider Implementation ---
 
 /// Provides interaction with OpenAI-compatible LLM APIs.
 ///
-/// Supports standard chat completion and non-streaming tool calls.
-/// Streaming tool calls are currently disabled due to parsing complexities.
+/// Supports standard chat completion and tool calls, both streaming and non-streaming.
 #[derive(Debug, Clone)]
 pub struct OpenAIProvider {
     config: LlmConfig,
     client: Client,
-    api_key: String,
     base_url: String,
+    /// Content type, authorization and OpenRouter headers, validated at construction.
+    headers: HeaderMap,
 }
 
 impl OpenAIProvider {
     /// Creates a new OpenAI provider instance from the given configuration.
-    /// Panics if the configuration is missing the required API key or if the HTTP client fails to build.
-    pub fn new(config: LlmConfig) -> Self {
-        let api_key = config
-            .api_key
-            .clone()
-            .expect("OpenAI provider requires an API key");
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::MissingConfig` if the API key is missing, and
+    /// `ProviderError::ConfigError` if it is not a valid header value or the HTTP client fails to build.
+    pub fn try_new(config: LlmConfig) -> Result<Self, ProviderError> {
+        let api_key = config.api_key.as_ref().ok_or_else(|| {
+            ProviderError::MissingConfig("OpenAI provider requires an API key".to_string())
+        })?;
+
+        let bearer = Zeroizing::new(format!("Bearer {}", api_key.expose_secret()));
+        let mut auth = HeaderValue::from_str(&bearer).map_err(|_| {
+            ProviderError::ConfigError(
+                "OpenAI API key is not a valid HTTP header value".to_string(),
+            )
+        })?;
+        auth.set_sensitive(true);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(AUTHORIZATION, auth);
 
         let base_url = config
             .base_url
@@ -189,35 +396,47 @@ impl OpenAIProvider {
         let client = Client::builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
             .build()
-            .expect("Failed to build Reqwest client");
+            .map_err(|e| {
+                ProviderError::ConfigError(format!("Failed to build HTTP client: {}", e))
+            })?;
 
-        Self { config, client, api_key, base_url }
+        let mut provider = Self {
+            config,
+            client,
+            base_url,
+            headers,
+        };
+        // Add OpenRouter-specific headers if using OpenRouter
+        if provider.is_openrouter() {
+            provider
+                .headers
+                .insert("HTTP-Referer", HeaderValue::from_static(APP_SITE_URL));
+            provider
+                .headers
+                .insert("X-Title", HeaderValue::from_static(APP_SITE_NAME));
+        }
+        Ok(provider)
     }
 
-    /// Builds the necessary HTTP headers for OpenAI API calls.
-    /// Adds OpenRouter-specific headers if the base URL contains "openrouter".
-    fn build_headers(&self) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", self.api_key))
-                .expect("Failed to create auth header"),
-        );
+    /// Creates a new OpenAI provider instance from the given configuration.
+    /// Panics if the configuration is invalid; use `try_new` to handle the error instead.
+    pub fn new(config: LlmConfig) -> Self {
+        Self::try_new(config).unwrap_or_else(|e| panic!("Failed to create OpenAI provider: {}", e))
+    }
 
-        // Add OpenRouter-specific headers if using OpenRouter
-        if self.base_url.to_lowercase().contains("openrouter") {
-            headers.insert(
-                "HTTP-Referer",
-                HeaderValue::from_static(APP_SITE_URL),
-            );
-            headers.insert(
-                "X-Title",
-                HeaderValue::from_static(APP_SITE_NAME  ),
-            );
-        }
+    /// Returns true if the base URL points at OpenRouter.
+    fn is_openrouter(&self) -> bool {
+        self.base_url.to_lowercase().contains("openrouter")
+    }
 
-        headers
+    /// Returns the provider's HTTP headers plus the request's extra headers.
+    fn build_headers(
+        &self,
+        extra_headers: Option<&HashMap<String, String>>,
+    ) -> Result<HeaderMap, ProviderError> {
+        let mut headers = self.headers.clone();
+        apply_extra_headers(&mut headers, extra_headers)?;
+        Ok(headers)
     }
 
     /// Maps the generic Tool structure to the OpenAI-specific format.
@@ -236,9 +455,150 @@ impl OpenAIProvider {
         })
     }
 
+    /// Maps the generic messages to OpenAI's format; inline images become `data:` URLs.
+    fn map_messages(messages: &[ChatMessage]) -> Vec<OpenAIRequestMessage> {
+        messages
+            .iter()
+            .map(|message| OpenAIRequestMessage {
+                role: message.role.clone(),
+                content: message.content.as_ref().map(|content| match content {
+                    MessageContent::Text(text) => OpenAIContent::Text(text.clone()),
+                    MessageContent::Parts(parts) => OpenAIContent::Parts(
+                        parts
+                            .iter()
+                            .map(|part| match part {
+                                ContentPart::Text { text } => {
+                                    OpenAIContentPart::Text { text: text.clone() }
+                                }
+                                ContentPart::ImageUrl { url } => OpenAIContentPart::ImageUrl {
+                                    image_url: OpenAIImageUrl { url: url.clone() },
+                                },
+                                ContentPart::ImageBase64 { media_type, data } => {
+                                    OpenAIContentPart::ImageUrl {
+                                        image_url: OpenAIImageUrl {
+                                            url: format!("data:{};base64,{}", media_type, data),
+                                        },
+                                    }
+                                }
+                            })
+                            .collect(),
+                    ),
+                }),
+                tool_calls: message.tool_calls.clone(),
+                tool_call_id: message.tool_call_id.clone(),
+            })
+            .collect()
+    }
+
+    /// Maps the generic tool choice to OpenAI's `tool_choice` value.
+    /// Only sent when tools are present; defaults to "auto".
+    fn map_tool_choice(request: &CompletionRequest) -> Option<JsonValue> {
+        request.tools.as_ref()?;
+        let choice = match request.tool_choice.as_ref().unwrap_or(&ToolChoice::Auto) {
+            ToolChoice::Auto => json!("auto"),
+            ToolChoice::None => json!("none"),
+            ToolChoice::Required => json!("required"),
+            ToolChoice::Function { name } => {
+                json!({ "type": "function", "function": { "name": name } })
+            }
+        };
+        Some(choice)
+    }
+
+    /// Maps the generic response format to OpenAI's `response_format` value.
+    fn map_response_format(response_format: Option<&ResponseFormat>) -> Option<JsonValue> {
+        response_format.map(|format| match format {
+            ResponseFormat::Text => json!({ "type": "text" }),
+            ResponseFormat::JsonObject => json!({ "type": "json_object" }),
+            ResponseFormat::JsonSchema {
+                name,
+                schema,
+                strict,
+            } => json!({
+                "type": "json_schema",
+                "json_schema": { "name": name, "schema": schema, "strict": strict },
+            }),
+        })
+    }
+
+    /// Builds the OpenAI request body from the generic request.
+    ///
+    /// `top_k` is passed through for OpenAI-compatible backends (OpenRouter, vLLM, ...) but
+    /// rejected for the official OpenAI API, which does not accept it. Stream chunks carry no
+    /// choice index, so `n > 1` is only accepted for non-streaming requests.
+    fn build_request(
+        &self,
+        request: &CompletionRequest,
+        stream: bool,
+    ) -> Result<OpenAIChatRequest, ProviderError> {
+        let mut unsupported = Vec::new();
+        if stream {
+            unsupported.push(SamplingParam::MultipleChoices);
+        }
+        if self.base_url.starts_with(OPENAI_BASE_URL) {
+            unsupported.push(SamplingParam::TopK);
+        }
+        ensure_supported("OpenAI", request, &unsupported)?;
+        let (reasoning_effort, reasoning) = self.map_reasoning(request.reasoning)?;
+        let (max_tokens, max_completion_tokens) =
+            if reasoning_effort.is_some() && self.base_url.starts_with(OPENAI_BASE_URL) {
+                (None, request.max_tokens)
+            } else {
+                (request.max_tokens, None)
+            };
+
+        Ok(OpenAIChatRequest {
+            model: request.model.clone(),
+            messages: Self::map_messages(&request.messages),
+            temperature: request.temperature,
+            max_tokens,
+            max_completion_tokens,
+            top_p: request.top_p,
+            top_k: request.top_k,
+            stop: request.stop.clone(),
+            seed: request.seed,
+            presence_penalty: request.presence_penalty,
+            frequency_penalty: request.frequency_penalty,
+            logit_bias: request.logit_bias.clone(),
+            n: request.n,
+            stream,
+            stream_options: stream.then(|| json!({ "include_usage": true })),
+            tools: Self::map_tools_to_openai(request.tools.as_ref()),
+            tool_choice: Self::map_tool_choice(request),
+            response_format: Self::map_response_format(request.response_format.as_ref()),
+            reasoning_effort,
+            reasoning,
+        })
+    }
+
+    /// Maps the reasoning controls to `reasoning_effort`, or to OpenRouter's `reasoning`
+    /// object, which also accepts a token budget and disabling reasoning.
+    fn map_reasoning(
+        &self,
+        reasoning: Option<Reasoning>,
+    ) -> Result<(Option<&'static str>, Option<JsonValue>), ProviderError> {
+        let Some(reasoning) = reasoning else {
+            return Ok((None, None));
+        };
+        if self.is_openrouter() {
+            let value = match reasoning {
+                Reasoning::Disabled => json!({ "enabled": false }),
+                Reasoning::Effort(effort) => json!({ "effort": effort.as_str() }),
+                Reasoning::BudgetTokens(max_tokens) => json!({ "max_tokens": max_tokens }),
+            };
+            return Ok((None, Some(value)));
+        }
+        match reasoning {
+            Reasoning::Effort(effort) => Ok((Some(effort.as_str()), None)),
+            Reasoning::Disabled | Reasoning::BudgetTokens(_) => Err(ProviderError::Unsupported(
+                "OpenAI only supports reasoning as an effort level".to_string(),
+            )),
+        }
+    }
+
     /// Maps the OpenAI usage structure to the generic TokenUsage structure.
     fn map_usage(usage: Option<OpenAIUsage>) -> Option<TokenUsage> {
-         usage.map(|u| TokenUsage {
+        usage.map(|u| TokenUsage {
             prompt_tokens: u.prompt_tokens,
             completion_tokens: u.completion_tokens,
             total_tokens: u.total_tokens,
@@ -260,238 +620,640 @@ impl OpenAIProvider {
             .collect()
     }
 
-    /// Determines the final CompletionKind based on the message content, tool calls, and finish reason.
-    fn determine_completion_kind(message: OpenAIMessage, finish_reason: Option<&str>) -> CompletionKind {
-        match (message.content, message.tool_calls) {
-            // If tool_calls are present, they take precedence, regardless of content.
-            (_, Some(tool_calls)) => {
-                CompletionKind::ToolCall { tool_calls: Self::map_tool_calls(tool_calls) }
+    /// Maps an OpenAI choice to the generic choice, keeping text, tool calls and refusal together.
+    fn map_choice(position: usize, choice: OpenAIChoice) -> CompletionChoice {
+        CompletionChoice {
+            index: choice.index.unwrap_or(position as u32),
+            content: choice.message.content,
+            tool_calls: Self::map_tool_calls(choice.message.tool_calls.unwrap_or_default()),
+            refusal: choice.message.refusal,
+            reasoning: choice
+                .message
+                .reasoning
+                .or(choice.message.reasoning_content),
+            reasoning_blocks: Vec::new(),
+            finish_reason: choice.finish_reason,
+        }
+    }
+
+    /// Translates one streamed OpenAI response into generic chunks.
+    ///
+    /// Text and tool call fragments are emitted as they arrive, so text interleaved
+    /// with tool calls is preserved. Tool call deltas carry only the new fragment;
+    /// consumers concatenate `arguments` per `index`.
+    fn map_stream_response(
+        openai_chunk: OpenAIChatStreamResponse,
+        tool_calls: &mut OpenAIToolCallTracker,
+    ) -> Vec<CompletionStreamChunk> {
+        let mut chunks = Vec::new();
+        let mut finish_reason = None;
+
+        if let Some(choice) = openai_chunk.choices.into_iter().next() {
+            finish_reason = choice.finish_reason;
+
+            let reasoning_delta = choice.delta.reasoning.or(choice.delta.reasoning_content);
+            if let Some(reasoning_delta) = reasoning_delta.filter(|t| !t.is_empty()) {
+                chunks.push(CompletionStreamChunk {
+                    delta: StreamContentDelta::Reasoning(reasoning_delta),
+                    usage: None,
+                    finish_reason: None,
+                });
             }
-            // If no tool_calls but content is present, it's a message.
-            (Some(content), None) => {
-                CompletionKind::Message { content }
+
+            if let Some(text_delta) = choice.delta.content.filter(|t| !t.is_empty()) {
+                chunks.push(CompletionStreamChunk {
+                    delta: StreamContentDelta::Text(text_delta),
+                    usage: None,
+                    finish_reason: None,
+                });
             }
-            // If neither content nor tool_calls are present, determine based on finish reason.
-            (None, None) => {
-                match finish_reason {
-                    Some("tool_calls") => {
-                        // Model intended to call tools but didn't provide them (edge case?).
-                        CompletionKind::ToolCall { tool_calls: vec![] }
-                    }
-                    _ => {
-                        // Finished normally or other reason, but content was empty/null.
-                        CompletionKind::Message { content: "".to_string() }
+
+            let generic_deltas: Vec<ToolCallStreamDelta> = choice
+                .delta
+                .tool_calls
+                .unwrap_or_default()
+                .into_iter()
+                .map(|tool_delta| {
+                    let index = tool_calls.resolve(tool_delta.index, tool_delta.id.as_deref());
+                    ToolCallStreamDelta {
+                        index,
+                        id: tool_delta.id,
+                        function: tool_delta.function.map(|f| ToolCallFunctionStreamDelta {
+                            name: f.name,
+                            arguments: f.arguments,
+                        }),
                     }
+                })
+                .collect();
+            if !generic_deltas.is_empty() {
+                chunks.push(CompletionStreamChunk {
+                    delta: StreamContentDelta::ToolCallDelta(generic_deltas),
+                    usage: None,
+                    finish_reason: None,
+                });
+            }
+        }
+
+        let usage = Self::map_usage(openai_chunk.usage);
+        if finish_reason.is_some() || usage.is_some() {
+            // Attach final info to the last chunk, or emit an empty one to carry it.
+            match chunks.last_mut() {
+                Some(last) => {
+                    last.usage = usage;
+                    last.finish_reason = finish_reason;
                 }
+                None => chunks.push(CompletionStreamChunk {
+                    delta: StreamContentDelta::Text("".to_string()), // Empty delta for final info
+                    usage,
+                    finish_reason,
+                }),
             }
         }
+
+        chunks
+    }
+
+    /// Maps an embeddings response, restoring input order from each entry's `index`.
+    fn map_embedding_response(mut response: OpenAIEmbeddingResponse) -> EmbeddingResponse {
+        response.data.sort_by_key(|entry| entry.index);
+        EmbeddingResponse {
+            embeddings: response
+                .data
+                .into_iter()
+                .map(|entry| entry.embedding)
+                .collect(),
+            usage: response.usage.map(|u| TokenUsage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: 0,
+                total_tokens: u.total_tokens,
+            }),
+        }
+    }
+
+    /// Maps a `/models` entry to the generic `ModelInfo`.
+    fn map_model(model: OpenAIModel) -> ModelInfo {
+        let pricing = model.pricing.and_then(|pricing| {
+            Some(ModelPricing {
+                prompt: pricing.prompt?.parse().ok()?,
+                completion: pricing.completion?.parse().ok()?,
+            })
+        });
+        ModelInfo {
+            id: model.id,
+            name: model.name,
+            context_window: model.context_length,
+            supports_tools: model
+                .supported_parameters
+                .map(|params| params.iter().any(|p| p == "tools")),
+            supports_vision: model
+                .architecture
+                .map(|arch| arch.input_modalities.iter().any(|m| m == "image")),
+            pricing,
+        }
+    }
+
+    /// Converts a non-success HTTP response into a `ProviderError::ApiError`,
+    /// keeping OpenAI's error `code`, `type` and `param`.
+    async fn api_error(res: reqwest::Response) -> ProviderError {
+        read_api_error(res, |body| {
+            let error = serde_json::from_str::<OpenAIErrorResponse>(body)
+                .ok()?
+                .error;
+            let code = error.code.map(|code| match code {
+                JsonValue::String(code) => code,
+                other => other.to_string(),
+            });
+            Some(ParsedApiError {
+                message: error.message,
+                code,
+                error_type: error.error_type,
+                param: error.param,
+            })
+        })
+        .await
+    }
+
+    /// Parses one SSE event into generic stream chunks.
+    fn process_event(
+        event: SseEvent,
+        tool_calls: &mut OpenAIToolCallTracker,
+    ) -> Result<Vec<CompletionStreamChunk>, ProviderError> {
+        if event.data.is_empty() {
+            return Ok(Vec::new());
+        }
+        let openai_chunk = serde_json::from_str::<OpenAIChatStreamResponse>(&event.data)?;
+        Ok(Self::map_stream_response(openai_chunk, tool_calls))
     }
 }
 
 #[async_trait]
 impl LlmProvider for OpenAIProvider {
     /// Generates a non-streaming completion, handling potential tool calls.
-    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError> {
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
         if self.config.provider != Provider::OpenAI {
             return Err(ProviderError::ConfigError(
                 "Invalid provider configured for OpenAIProvider".to_string(),
             ));
         }
 
-        let openai_request = OpenAIChatRequest {
-            model: request.model.clone(),
-            messages: request.messages.clone(),
-            temperature: request.temperature,
-            max_tokens: request.max_tokens,
-            stream: false,
-            tools: Self::map_tools_to_openai(request.tools.as_ref()),
-            // Default to auto tool choice if tools are present, allows user override later
-            tool_choice: request.tools.as_ref().map(|_| json!("auto")), 
-        };
+        let openai_request = self.build_request(&request, false)?;
 
         let url = format!("{}/chat/completions", self.base_url);
-        let headers = self.build_headers();
+        let headers = self.build_headers(request.extra_headers.as_ref())?;
+        let body = merge_extra_body(&openai_request, request.extra_body.as_ref())?;
 
-        let res = self.client.post(&url).headers(headers).json(&openai_request).send().await?;
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&body),
+        )
+        .await?;
 
         if !res.status().is_success() {
-            let status = res.status().as_u16();
-            let error_body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-            // Try to parse OpenAI specific error
-             let message = serde_json::from_str::<OpenAIErrorResponse>(&error_body)
-                 .map(|e| e.error.message)
-                 .unwrap_or(error_body); // Fallback to full body
-            return Err(ProviderError::ApiError { status, message });
+            return Err(Self::api_error(res).await);
         }
 
         let openai_response: OpenAIChatResponse = res.json().await?;
 
-        let first_choice = openai_response.choices.into_iter().next()
-            .ok_or_else(|| ProviderError::ParseError(serde_json::Error::custom("No choices found in OpenAI response")))?;
-
-        let usage = Self::map_usage(openai_response.usage);
-        // Extract finish_reason before moving message into the helper
-        let finish_reason = first_choice.finish_reason.clone(); 
+        if openai_response.choices.is_empty() {
+            return Err(ProviderError::ParseError(serde_json::Error::custom(
+                "No choices found in OpenAI response",
+            )));
+        }
 
-        // Use the helper function to determine the kind (pass only message)
-        let kind = Self::determine_completion_kind(first_choice.message, finish_reason.as_deref()); 
+        let choices = openai_response
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(position, choice)| Self::map_choice(position, choice))
+            .collect();
 
         Ok(CompletionResponse {
-            kind,
-            usage,
-            finish_reason, // Use the extracted finish_reason
+            choices,
+            usage: Self::map_usage(openai_response.usage),
         })
     }
 
-    /// Generates a streaming completion.
-    /// NOTE: Tool calls are currently unsupported in streaming mode for this provider.
+    /// Generates a streaming completion, including streamed tool calls.
     async fn completion_stream(
         &self,
         request: CompletionRequest,
     ) -> Result<CompletionStream, ProviderError> {
-        // --- Temporarily disabled due to SSE parsing fragility ---
-        if request.tools.is_some() {
-            return Err(ProviderError::Unsupported(
-                "Streaming tool calls are not currently supported by the OpenAI provider implementation.".to_string()
-            ));
-        }
-        // --- End Temporary ---
-
         if self.config.provider != Provider::OpenAI {
             return Err(ProviderError::ConfigError(
                 "Invalid provider configured for OpenAIProvider".to_string(),
             ));
         }
 
-        let openai_request = OpenAIChatRequest {
-            model: request.model.clone(),
-            messages: request.messages.clone(),
-            temperature: request.temperature,
-            max_tokens: request.max_tokens,
-            stream: true,
-            tools: None, // Ensure tools are None for stream request
-            tool_choice: None, // Ensure tool_choice is None for stream request
-        };
+        let openai_request = self.build_request(&request, true)?;
 
         let url = format!("{}/chat/completions", self.base_url);
-        let headers = self.build_headers();
+        let headers = self.build_headers(request.extra_headers.as_ref())?;
+        let body = merge_extra_body(&openai_request, request.extra_body.as_ref())?;
 
-        let res = self.client.post(&url).headers(headers).json(&openai_request).send().await?;
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client.post(&url).headers(headers).json(&body),
+        )
+        .await?;
 
         if !res.status().is_success() {
-            let status = res.status().as_u16();
-            let error_body = res.text().await.unwrap_or_else(|_| "Failed to read error body".to_string());
-             let message = serde_json::from_str::<OpenAIErrorResponse>(&error_body)
-                 .map(|e| e.error.message)
-                 .unwrap_or(error_body); 
-            return Err(ProviderError::ApiError { status, message });
+            return Err(Self::api_error(res).await);
         }
 
-        let sse_stream = res.bytes_stream().map_err(ProviderError::RequestError);
-
-        // State for aggregating tool calls, wrapped for async stream handling
-        let tool_call_aggregator = Arc::new(Mutex::new(HashMap::<usize, ToolCallStreamDelta>::new()));
-
-        let chunk_stream = sse_stream.try_filter_map(move |chunk: Bytes| {
-            let state_lock = Arc::clone(&tool_call_aggregator);
-            async move {
-                let lines = chunk.split(|&b| b == b'\n');
-                let mut result_chunk: Option<CompletionStreamChunk> = None;
-                let mut final_usage: Option<OpenAIUsage> = None;
-                let mut final_reason: Option<String> = None;
-
-                // Process each line in the chunk
-                for line in lines {
-                    if line.starts_with(b"data: ") {
-                        let data = &line[6..];
-                        if data.is_empty() || data == b"[DONE]" {
-                            continue;
-                        }
-
-                        match serde_json::from_slice::<OpenAIChatStreamResponse>(data) {
-                            Ok(openai_chunk) => {
-                                if let Some(usage) = openai_chunk.usage {
-                                    final_usage = Some(usage); // Capture final usage if present
-                                }
+        // Tracks tool call indexes across events; owned by the stream handler
+        let mut tool_call_tracker = OpenAIToolCallTracker::default();
 
-                                if let Some(choice) = openai_chunk.choices.into_iter().next() {
-                                     if let Some(reason) = choice.finish_reason {
-                                         final_reason = Some(reason); // Capture final reason
-                                     }
-
-                                    // Lock mutex to process delta content
-                                    let mut current_tool_calls = state_lock.lock().map_err(|_| {
-                                        ProviderError::Unexpected("Mutex poisoned in stream processing".to_string())
-                                    })?;
-
-                                    if let Some(text_delta) = choice.delta.content {
-                                        if !text_delta.is_empty() {
-                                            result_chunk = Some(CompletionStreamChunk {
-                                                delta: StreamContentDelta::Text(text_delta),
-                                                usage: None,
-                                                finish_reason: None,
-                                            });
-                                            current_tool_calls.clear(); // Clear tool state if text received
-                                        }
-                                    } else if let Some(tool_deltas) = choice.delta.tool_calls {
-                                        let mut generic_deltas = Vec::new();
-                                        for tool_delta in tool_deltas {
-                                            let entry = current_tool_calls
-                                                .entry(tool_delta.index)
-                                                .or_insert_with(|| ToolCallStreamDelta {
-                                                    index: tool_delta.index, id: None, function: None,
-                                                });
-
-                                            // Aggregate parts into the entry in the shared state
-                                            if let Some(id) = tool_delta.id { entry.id = Some(id); }
-                                            if let Some(func_delta) = tool_delta.function {
-                                                let func_entry = entry.function.get_or_insert_with(|| {
-                                                    ToolCallFunctionStreamDelta { name: None, arguments: None }
-                                                });
-                                                if let Some(name) = func_delta.name { func_entry.name = Some(name); }
-                                                if let Some(args_chunk) = func_delta.arguments {
-                                                     // DEBUG prints removed
-                                                     let current_args = func_entry.arguments.get_or_insert_with(String::new);
-                                                     current_args.push_str(&args_chunk);
-                                                 }
-                                            }
-                                            // Add a *clone* of the current aggregated state to the output chunk
-                                            generic_deltas.push(entry.clone()); 
-                                        }
-                                        // Only create a chunk if we actually processed deltas
-                                        if !generic_deltas.is_empty() {
-                                            result_chunk = Some(CompletionStreamChunk {
-                                                delta: StreamContentDelta::ToolCallDelta(generic_deltas),
-                                                usage: None,
-                                                finish_reason: None,
-                                            });
-                                        }
-                                    }
-                                    // Mutex guard dropped here implicitly
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Failed to parse OpenAI SSE chunk: {}, data: {}", e, String::from_utf8_lossy(data));
-                                // Decide whether to stop stream on parse error
-                                return Err(ProviderError::ParseError(e)); 
-                            }
-                        }
-                    }
-                }
-                
-                // If no data chunk was generated, but we got final usage/reason, create a final chunk
-                if result_chunk.is_none() && (final_reason.is_some() || final_usage.is_some()) {
-                     result_chunk = Some(CompletionStreamChunk {
-                         delta: StreamContentDelta::Text("".to_string()), // Empty delta for final info
-                         usage: Self::map_usage(final_usage),
-                         finish_reason: final_reason,
-                     });
-                 }
-
-                 Ok(result_chunk) // Return Option<CompletionStreamChunk>
-            }
+        Ok(decode_sse_stream(res.bytes_stream(), move |event| {
+            Self::process_event(event, &mut tool_call_tracker)
+        }))
+    }
+
+    /// Lists models through `GET /models`, including OpenRouter's metadata when present.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let url = format!("{}/models", self.base_url);
+        let headers = self.build_headers(None)?;
+
+        let res =
+            send_with_retry(&self.config.retry, self.client.get(&url).headers(headers)).await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
+        }
+
+        let model_list: OpenAIModelList = res.json().await?;
+        Ok(model_list.data.into_iter().map(Self::map_model).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAIProvider {
+    /// Computes embeddings through the `/embeddings` endpoint.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError> {
+        let embedding_request = OpenAIEmbeddingRequest {
+            model: &request.model,
+            input: &request.input,
+            dimensions: request.dimensions,
+        };
+
+        let url = format!("{}/embeddings", self.base_url);
+        let headers = self.build_headers(None)?;
+
+        let res = send_with_retry(
+            &self.config.retry,
+            self.client
+                .post(&url)
+                .headers(headers)
+                .json(&embedding_request),
+        )
+        .await?;
+
+        if !res.status().is_success() {
+            return Err(Self::api_error(res).await);
+        }
+
+        let openai_response: OpenAIEmbeddingResponse = res.json().await?;
+        Ok(Self::map_embedding_response(openai_response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use futures::stream::{self, StreamExt};
+
+    #[test]
+    fn test_try_new_rejects_invalid_api_key() {
+        let config = LlmConfig::new(Provider::OpenAI).with_api_key("sk-bad\nkey".to_string());
+        assert!(matches!(
+            OpenAIProvider::try_new(config.clone()),
+            Err(ProviderError::ConfigError(_))
+        ));
+        assert!(matches!(
+            crate::get_provider(config),
+            Err(ProviderError::ConfigError(_))
+        ));
+
+        let missing = OpenAIProvider::try_new(LlmConfig::new(Provider::OpenAI));
+        assert!(matches!(missing, Err(ProviderError::MissingConfig(_))));
+
+        let provider = OpenAIProvider::try_new(
+            LlmConfig::new(Provider::OpenAI).with_api_key("sk-secret".to_string()),
+        )
+        .unwrap();
+        assert!(!format!("{:?}", provider.build_headers(None).unwrap()).contains("sk-secret"));
+    }
+
+    /// Runs raw network chunks through the SSE decoder and the OpenAI event handler.
+    fn decode(network_chunks: Vec<&'static str>) -> Vec<CompletionStreamChunk> {
+        let mut tracker = OpenAIToolCallTracker::default();
+        let bytes = stream::iter(
+            network_chunks
+                .into_iter()
+                .map(|c| Ok(Bytes::from_static(c.as_bytes()))),
+        );
+        let chunk_stream = decode_sse_stream(bytes, move |event| {
+            OpenAIProvider::process_event(event, &mut tracker)
         });
+        futures::executor::block_on(chunk_stream.collect::<Vec<_>>())
+            .into_iter()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+    }
+
+    fn tool_deltas(chunk: &CompletionStreamChunk) -> &[ToolCallStreamDelta] {
+        match &chunk.delta {
+            StreamContentDelta::ToolCallDelta(deltas) => deltas,
+            other => panic!("expected tool call delta, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stream_parallel_tool_calls_with_text() {
+        let data = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Checking.\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"function\":{\"name\":\"add\",\"arguments\":\"\"}}]},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"a\\\":1}\"}}]},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":1,\"id\":\"call_b\",\"function\":{\"name\":\"mul\",\"arguments\":\"{}\"}}]},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+
+        let chunks = decode(vec![data]);
+        assert_eq!(chunks.len(), 5);
+        assert!(matches!(&chunks[0].delta, StreamContentDelta::Text(t) if t == "Checking."));
+
+        let first = &tool_deltas(&chunks[1])[0];
+        assert_eq!((first.index, first.id.as_deref()), (0, Some("call_a")));
+        let args = &tool_deltas(&chunks[2])[0];
+        assert_eq!(args.index, 0);
+        assert_eq!(
+            args.function.as_ref().unwrap().arguments.as_deref(),
+            Some("{\"a\":1}")
+        );
+        let second = &tool_deltas(&chunks[3])[0];
+        assert_eq!((second.index, second.id.as_deref()), (1, Some("call_b")));
+        assert_eq!(chunks[4].finish_reason, Some(FinishReason::ToolCalls));
+    }
+
+    #[test]
+    fn test_stream_event_split_across_reads() {
+        let chunks = decode(vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"},\"finish_",
+            "reason\":null}]}\r\n\r\ndata: {\"choices\":[{\"delta\":{\"content\":\"lo\"},\"finish_reason\":null}]}\r\n\r\n",
+            ": OPENROUTER PROCESSING\n\ndata: {\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\ndata: [DONE]\n\n",
+        ]);
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| match &c.delta {
+                StreamContentDelta::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Hello");
+        assert_eq!(
+            chunks.last().unwrap().finish_reason,
+            Some(FinishReason::Stop)
+        );
+    }
+
+    #[test]
+    fn test_stream_requests_and_keeps_usage_chunk() {
+        let provider = OpenAIProvider::try_new(
+            LlmConfig::new(Provider::OpenAI).with_api_key("test-key".to_string()),
+        )
+        .unwrap();
+        let body = serde_json::to_value(
+            provider
+                .build_request(&CompletionRequest::default(), true)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body["stream_options"], json!({ "include_usage": true }));
+        let body = serde_json::to_value(
+            provider
+                .build_request(&CompletionRequest::default(), false)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(body.get("stream_options"), None);
+
+        let chunks = decode(vec![
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":1,\"total_tokens\":10}}\n\n",
+            "data: [DONE]\n\n",
+        ]);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].usage.map(|usage| usage.total_tokens), Some(10));
+    }
+
+    #[test]
+    fn test_map_response_format_json_schema() {
+        let schema = json!({"type": "object", "properties": {"city": {"type": "string"}}});
+        let format = ResponseFormat::json_schema("location", schema.clone());
+        let mapped = OpenAIProvider::map_response_format(Some(&format)).unwrap();
+        assert_eq!(mapped["type"], "json_schema");
+        assert_eq!(mapped["json_schema"]["name"], "location");
+        assert_eq!(mapped["json_schema"]["strict"], true);
+        assert_eq!(mapped["json_schema"]["schema"], schema);
+    }
+
+    #[test]
+    fn test_tracker_separates_calls_sharing_upstream_index() {
+        let mut tracker = OpenAIToolCallTracker::default();
+        assert_eq!(tracker.resolve(Some(0), Some("call_a")), 0);
+        assert_eq!(tracker.resolve(Some(0), None), 0);
+        assert_eq!(tracker.resolve(Some(0), Some("call_b")), 1);
+        assert_eq!(tracker.resolve(Some(0), None), 1);
+        assert_eq!(tracker.resolve(None, Some("call_a")), 0);
+    }
+
+    #[test]
+    fn test_map_messages_content_parts() {
+        let messages = vec![
+            ChatMessage::system("Describe images.".to_string()),
+            ChatMessage::user_parts(vec![
+                ContentPart::text("What is this?"),
+                ContentPart::image_url("https://example.com/cat.png"),
+                ContentPart::image_base64("image/png", "iVBORw0KGgo="),
+            ]),
+        ];
+        let value = serde_json::to_value(OpenAIProvider::map_messages(&messages)).unwrap();
+        assert_eq!(value[0]["content"], "Describe images.");
+        assert_eq!(
+            value[1]["content"],
+            json!([
+                { "type": "text", "text": "What is this?" },
+                { "type": "image_url", "image_url": { "url": "https://example.com/cat.png" } },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_map_choices_keeps_text_tool_calls_and_refusal() {
+        let response: OpenAIChatResponse = serde_json::from_value(json!({
+            "choices": [
+                {
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "I'll look that up.",
+                        "tool_calls": [{ "id": "call_1", "type": "function", "function": { "name": "search", "arguments": "{}" } }]
+                    },
+                    "finish_reason": "tool_calls"
+                },
+                {
+                    "index": 1,
+                    "message": { "role": "assistant", "content": null, "refusal": "I can't help with that." },
+                    "finish_reason": "stop"
+                }
+            ]
+        }))
+        .unwrap();
+
+        let choices: Vec<CompletionChoice> = response
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(position, choice)| OpenAIProvider::map_choice(position, choice))
+            .collect();
+        assert_eq!(choices[0].content.as_deref(), Some("I'll look that up."));
+        assert_eq!(choices[0].tool_calls[0].function.name, "search");
+        assert_eq!(choices[0].finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(choices[1].index, 1);
+        assert_eq!(
+            choices[1].refusal.as_deref(),
+            Some("I can't help with that.")
+        );
+        assert_eq!(choices[1].finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[test]
+    fn test_stream_reasoning_deltas() {
+        let chunks = decode(vec![
+            "data: {\"choices\":[{\"delta\":{\"reasoning_content\":\"Thinking\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"reasoning\":\" hard.\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"42\"},\"finish_reason\":\"stop\"}]}\n\n",
+        ]);
+        let reasoning: String = chunks
+            .iter()
+            .filter_map(|c| match &c.delta {
+                StreamContentDelta::Reasoning(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasoning, "Thinking hard.");
+        assert!(matches!(&chunks[2].delta, StreamContentDelta::Text(t) if t == "42"));
+    }
+
+    #[test]
+    fn test_map_reasoning_per_backend() {
+        let provider = |base_url: &str| {
+            OpenAIProvider::try_new(
+                LlmConfig::new(Provider::OpenAI)
+                    .with_api_key("test-key".to_string())
+                    .with_base_url(base_url.to_string()),
+            )
+            .unwrap()
+        };
+        let openai = provider(OPENAI_BASE_URL);
+        let openrouter = provider("https://openrouter.ai/api/v1");
+
+        let (effort, _) = openai
+            .map_reasoning(Some(Reasoning::Effort(
+                crate::traits::ReasoningEffort::High,
+            )))
+            .unwrap();
+        assert_eq!(effort, Some("high"));
+        assert!(matches!(
+            openai.map_reasoning(Some(Reasoning::BudgetTokens(2048))),
+            Err(ProviderError::Unsupported(_))
+        ));
 
-        Ok(Box::pin(chunk_stream))
+        let (effort, reasoning) = openrouter
+            .map_reasoning(Some(Reasoning::BudgetTokens(2048)))
+            .unwrap();
+        assert_eq!(effort, None);
+        assert_eq!(reasoning, Some(json!({ "max_tokens": 2048 })));
+
+        // OpenAI's reasoning models take `max_completion_tokens` instead of `max_tokens`.
+        let mut request = CompletionRequest {
+            model: "o4-mini".to_string(),
+            max_tokens: Some(512),
+            reasoning: Some(Reasoning::Effort(crate::traits::ReasoningEffort::High)),
+            ..Default::default()
+        };
+        let body = serde_json::to_value(openai.build_request(&request, false).unwrap()).unwrap();
+        assert_eq!(
+            (body.get("max_tokens"), &body["max_completion_tokens"]),
+            (None, &json!(512))
+        );
+        request.reasoning = None;
+        let body = serde_json::to_value(openai.build_request(&request, false).unwrap()).unwrap();
+        assert_eq!(
+            (&body["max_tokens"], body.get("max_completion_tokens")),
+            (&json!(512), None)
+        );
+    }
+
+    #[test]
+    fn test_map_embedding_response_restores_input_order() {
+        let response: OpenAIEmbeddingResponse = serde_json::from_value(json!({
+            "object": "list",
+            "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.3, 0.4] },
+                { "object": "embedding", "index": 0, "embedding": [0.1, 0.2] }
+            ],
+            "usage": { "prompt_tokens": 6, "total_tokens": 6 }
+        }))
+        .unwrap();
+        let mapped = OpenAIProvider::map_embedding_response(response);
+        assert_eq!(mapped.embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+        assert_eq!(mapped.usage.unwrap().prompt_tokens, 6);
+    }
+
+    #[test]
+    fn test_map_model_openrouter_metadata() {
+        let list: OpenAIModelList = serde_json::from_value(json!({
+            "data": [
+                {
+                    "id": "openai/gpt-4o",
+                    "name": "OpenAI: GPT-4o",
+                    "context_length": 128000,
+                    "pricing": { "prompt": "0.0000025", "completion": "0.00001" },
+                    "architecture": { "input_modalities": ["text", "image"], "output_modalities": ["text"] },
+                    "supported_parameters": ["tools", "tool_choice", "temperature"]
+                },
+                { "id": "gpt-4o-mini", "object": "model", "created": 1721172741, "owned_by": "system" }
+            ]
+        }))
+        .unwrap();
+        let models: Vec<ModelInfo> = list
+            .data
+            .into_iter()
+            .map(OpenAIProvider::map_model)
+            .collect();
+
+        assert_eq!(models[0].context_window, Some(128000));
+        assert_eq!(models[0].supports_tools, Some(true));
+        assert_eq!(models[0].supports_vision, Some(true));
+        assert_eq!(
+            models[0].pricing,
+            Some(ModelPricing {
+                prompt: 0.0000025,
+                completion: 0.00001
+            })
+        );
+        assert_eq!(
+            models[1],
+            ModelInfo {
+                id: "gpt-4o-mini".to_string(),
+                ..Default::default()
+            }
+        );
     }
-} 
\ No newline at end of file
+}
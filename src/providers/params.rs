@@ -0,0 +1,71 @@
+//!
+//! Request Parameter Checks
+//!
+//! Providers differ in which sampling parameters they accept. Rather than silently
+//! dropping a parameter the caller set, each provider lists the ones it cannot honour
+//! and the request is rejected with `ProviderError::Unsupported`.
+
+use crate::traits::{CompletionRequest, ProviderError};
+
+/// A sampling parameter of `CompletionRequest` that not every provider supports.
+/// (`top_p` and `stop` are honoured everywhere and so are not listed.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SamplingParam {
+    TopK,
+    Seed,
+    PresencePenalty,
+    FrequencyPenalty,
+    LogitBias,
+    /// More than one choice (`n > 1`).
+    MultipleChoices,
+}
+
+impl SamplingParam {
+    /// The request field name, as shown in error messages.
+    fn name(self) -> &'static str {
+        match self {
+            SamplingParam::TopK => "top_k",
+            SamplingParam::Seed => "seed",
+            SamplingParam::PresencePenalty => "presence_penalty",
+            SamplingParam::FrequencyPenalty => "frequency_penalty",
+            SamplingParam::LogitBias => "logit_bias",
+            SamplingParam::MultipleChoices => "n > 1",
+        }
+    }
+
+    /// Whether the request sets this parameter.
+    fn is_set(self, request: &CompletionRequest) -> bool {
+        match self {
+            SamplingParam::TopK => request.top_k.is_some(),
+            SamplingParam::Seed => request.seed.is_some(),
+            SamplingParam::PresencePenalty => request.presence_penalty.is_some(),
+            SamplingParam::FrequencyPenalty => request.frequency_penalty.is_some(),
+            SamplingParam::LogitBias => request
+                .logit_bias
+                .as_ref()
+                .is_some_and(|bias| !bias.is_empty()),
+            SamplingParam::MultipleChoices => request.n.is_some_and(|n| n > 1),
+        }
+    }
+}
+
+/// Returns `ProviderError::Unsupported` naming every parameter in `unsupported` that the request sets.
+pub(crate) fn ensure_supported(
+    provider: &str,
+    request: &CompletionRequest,
+    unsupported: &[SamplingParam],
+) -> Result<(), ProviderError> {
+    let set: Vec<&str> = unsupported
+        .iter()
+        .filter(|param| param.is_set(request))
+        .map(|param| param.name())
+        .collect();
+    if set.is_empty() {
+        return Ok(());
+    }
+    Err(ProviderError::Unsupported(format!(
+        "{} does not support the request parameter(s): {}",
+        provider,
+        set.join(", ")
+    )))
+}
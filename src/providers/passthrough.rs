@@ -0,0 +1,83 @@
+//!
+//! Request Passthrough
+//!
+//! Merges the caller-supplied `extra_body` and `extra_headers` of a `CompletionRequest`
+//! into the outgoing provider request, so provider parameters this crate does not model
+//! yet can still be sent.
+
+use crate::traits::ProviderError;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::HashMap;
+
+/// Serializes `body` and merges `extra_body` into it at the top level.
+/// Keys in `extra_body` replace fields of the same name set by the provider.
+pub(crate) fn merge_extra_body<T: Serialize>(
+    body: &T,
+    extra_body: Option<&Map<String, JsonValue>>,
+) -> Result<JsonValue, ProviderError> {
+    let mut value = serde_json::to_value(body)?;
+    if let Some(extra_body) = extra_body {
+        let object = value.as_object_mut().ok_or_else(|| {
+            ProviderError::Unexpected("Provider request body is not a JSON object".to_string())
+        })?;
+        object.extend(
+            extra_body
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    }
+    Ok(value)
+}
+
+/// Inserts `extra_headers` into `headers`, replacing headers of the same name.
+pub(crate) fn apply_extra_headers(
+    headers: &mut HeaderMap,
+    extra_headers: Option<&HashMap<String, String>>,
+) -> Result<(), ProviderError> {
+    for (name, value) in extra_headers.into_iter().flatten() {
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+            ProviderError::ConfigError(format!("Invalid extra header name '{}': {}", name, e))
+        })?;
+        let header_value = HeaderValue::from_str(value).map_err(|e| {
+            ProviderError::ConfigError(format!("Invalid value for extra header '{}': {}", name, e))
+        })?;
+        headers.insert(header_name, header_value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extra_body_overrides_and_extends() {
+        let extra = json!({ "keep_alive": "5m", "stream": true });
+        let body = merge_extra_body(
+            &json!({ "model": "llama3", "stream": false }),
+            extra.as_object(),
+        )
+        .unwrap();
+        assert_eq!(
+            body,
+            json!({ "model": "llama3", "stream": true, "keep_alive": "5m" })
+        );
+    }
+
+    #[test]
+    fn test_invalid_extra_header_is_config_error() {
+        let mut headers = HeaderMap::new();
+        let extra = HashMap::from([("X-Ok".to_string(), "1".to_string())]);
+        apply_extra_headers(&mut headers, Some(&extra)).unwrap();
+        assert_eq!(headers["x-ok"], "1");
+
+        let extra = HashMap::from([("Bad Header".to_string(), "1".to_string())]);
+        assert!(matches!(
+            apply_extra_headers(&mut headers, Some(&extra)),
+            Err(ProviderError::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+//!
+//! Request Retries
+//!
+//! Sends provider HTTP requests under the `RetryPolicy` of the provider's `LlmConfig`,
+//! retrying retryable statuses and connection failures with backoff or the delay the
+//! server asked for.
+
+use crate::config::RetryPolicy;
+use crate::traits::ProviderError;
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Sends `request`, retrying according to `policy`.
+///
+/// Returns the first response that is not retryable, or the last one once attempts run out,
+/// so callers still map error statuses themselves. Requests whose body cannot be cloned
+/// (streaming bodies) are sent once.
+pub(crate) async fn send_with_retry(
+    policy: &RetryPolicy,
+    request: RequestBuilder,
+) -> Result<Response, ProviderError> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut retry = 0;
+
+    loop {
+        let is_last_attempt = retry + 1 >= max_attempts;
+        let attempt = if is_last_attempt {
+            None
+        } else {
+            request.try_clone()
+        };
+        let Some(attempt) = attempt else {
+            return Ok(request.send().await?);
+        };
+
+        let delay = match attempt.send().await {
+            Ok(res) if policy.is_retryable_status(res.status().as_u16()) => {
+                server_delay(res.status().as_u16(), res.headers(), SystemTime::now())
+                    .map(|delay| delay.min(policy.max_delay))
+                    .unwrap_or_else(|| policy.backoff_delay(retry, random_fraction()))
+            }
+            Ok(res) => return Ok(res),
+            Err(e) if is_retryable_error(&e) => policy.backoff_delay(retry, random_fraction()),
+            Err(e) => return Err(e.into()),
+        };
+
+        tokio::time::sleep(delay).await;
+        retry += 1;
+    }
+}
+
+/// Connection failures, resets and timeouts happen before a response arrives and are
+/// worth another attempt; errors building the request are not.
+fn is_retryable_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || (error.is_request() && !error.is_builder())
+}
+
+/// Reads the delay the server asked for from the response headers.
+///
+/// `retry-after-ms` and `retry-after` (seconds or HTTP date) take precedence. For 429s only,
+/// the rate limit reset headers are used next: `x-ratelimit-reset-*` (OpenAI durations like
+/// `6m0s`) and `x-ratelimit-reset` (OpenRouter's epoch milliseconds). The bucket whose
+/// matching `x-ratelimit-remaining*` header is 0 is the one that was hit, so its reset wins;
+/// without such a header the longest reset is used.
+pub(crate) fn server_delay(status: u16, headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+
+    if let Some(ms) = header("retry-after-ms").and_then(|value| value.parse::<f64>().ok()) {
+        return seconds(ms / 1000.0);
+    }
+    if let Some(value) = header("retry-after") {
+        if let Ok(secs) = value.parse::<f64>() {
+            return seconds(secs);
+        }
+        if let Some(at) = parse_http_date(value) {
+            return Some(at.duration_since(now).unwrap_or_default());
+        }
+    }
+
+    if status != 429 {
+        return None;
+    }
+    // (delay, whether the bucket is used up) for every reset header.
+    let resets: Vec<(Duration, bool)> = headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().ok()?.trim();
+            let bucket = name.as_str().strip_prefix("x-ratelimit-reset")?;
+            let delay = match bucket {
+                "" => parse_reset_timestamp(value, now)?,
+                bucket if bucket.starts_with('-') => parse_reset_duration(value)?,
+                _ => return None,
+            };
+            let exhausted = header(&format!("x-ratelimit-remaining{}", bucket))
+                .and_then(|v| v.parse::<f64>().ok())
+                == Some(0.0);
+            Some((delay, exhausted))
+        })
+        .collect();
+
+    let exhausted = resets
+        .iter()
+        .filter(|(_, exhausted)| *exhausted)
+        .map(|(delay, _)| *delay)
+        .max();
+    exhausted.or_else(|| resets.iter().map(|(delay, _)| *delay).max())
+}
+
+/// Converts a non-negative, finite number of seconds to a `Duration`.
+fn seconds(secs: f64) -> Option<Duration> {
+    (secs.is_finite() && secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// Parses durations like `1s`, `20ms`, `6m0s` or `1h2m3.5s`.
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let factor = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        total += number * factor;
+        rest = &rest[unit_len..];
+    }
+    seconds(total)
+}
+
+/// Parses a reset time given as a Unix timestamp (seconds or milliseconds) into the delay
+/// from `now`. Small values are treated as a number of seconds to wait.
+fn parse_reset_timestamp(value: &str, now: SystemTime) -> Option<Duration> {
+    let number: f64 = value.parse().ok()?;
+    let at = if number >= 1e12 {
+        UNIX_EPOCH + Duration::from_secs_f64(number / 1000.0)
+    } else if number >= 1e9 {
+        UNIX_EPOCH + Duration::from_secs_f64(number)
+    } else {
+        return seconds(number);
+    };
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+/// Parses an IMF-fixdate HTTP date such as `Wed, 21 Oct 2015 07:28:00 GMT`.
+fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let mut parts = value.split_whitespace();
+    let _weekday = parts.next()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month = match parts.next()? {
+        "Jan" => 1,
+        "Feb" => 2,
+        "Mar" => 3,
+        "Apr" => 4,
+        "May" => 5,
+        "Jun" => 6,
+        "Jul" => 7,
+        "Aug" => 8,
+        "Sep" => 9,
+        "Oct" => 10,
+        "Nov" => 11,
+        "Dec" => 12,
+        _ => return None,
+    };
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts
+        .next()?
+        .split(':')
+        .map(|part| part.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    if parts.next()? != "GMT" || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60
+    {
+        return None;
+    }
+
+    // Days since the Unix epoch for a proleptic Gregorian date.
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = u64::try_from(era * 146_097 + day_of_era - 719_468).ok()?;
+
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86_400 + hour * 3600 + minute * 60 + second))
+}
+
+/// A cheap random number in `[0, 1)` for jitter, seeded by the std hasher's random keys.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_is_capped() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(500))
+            .with_jitter(0.5);
+
+        assert_eq!(policy.backoff_delay(0, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff_delay(2, 0.0), Duration::from_millis(400));
+        assert_eq!(policy.backoff_delay(10, 0.0), Duration::from_millis(500));
+        // Full jitter removes up to half of the delay.
+        assert_eq!(policy.backoff_delay(1, 1.0), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_retry_after_headers_take_precedence() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_445_412_470);
+
+        let delay = server_delay(
+            429,
+            &headers(&[("retry-after", "2"), ("x-ratelimit-reset-requests", "9s")]),
+            now,
+        );
+        assert_eq!(delay, Some(Duration::from_secs(2)));
+
+        let delay = server_delay(
+            429,
+            &headers(&[("retry-after-ms", "250"), ("retry-after", "2")]),
+            now,
+        );
+        assert_eq!(delay, Some(Duration::from_millis(250)));
+
+        let delay = server_delay(
+            429,
+            &headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]),
+            now,
+        );
+        assert_eq!(delay, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_rate_limit_reset_headers_prefer_the_exhausted_bucket() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let resets = [
+            ("x-ratelimit-reset-requests", "20ms"),
+            ("x-ratelimit-reset-tokens", "1m30.5s"),
+        ];
+        assert_eq!(
+            server_delay(429, &headers(&resets), now),
+            Some(Duration::from_millis(90_500))
+        );
+
+        let exhausted = [
+            ("x-ratelimit-reset-requests", "20ms"),
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-tokens", "1m30.5s"),
+            ("x-ratelimit-remaining-tokens", "15000"),
+        ];
+        assert_eq!(
+            server_delay(429, &headers(&exhausted), now),
+            Some(Duration::from_millis(20))
+        );
+
+        // Reset headers describe quota, not outages, so other statuses ignore them.
+        assert_eq!(server_delay(503, &headers(&resets), now), None);
+        assert_eq!(
+            server_delay(503, &headers(&[("retry-after", "2")]), now),
+            Some(Duration::from_secs(2))
+        );
+
+        let delay = server_delay(
+            429,
+            &headers(&[("x-ratelimit-reset", "1700000003000")]),
+            now,
+        );
+        assert_eq!(delay, Some(Duration::from_secs(3)));
+
+        assert_eq!(
+            server_delay(429, &headers(&[("x-ratelimit-reset-tokens", "soon")]), now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parses_http_dates() {
+        let parsed = parse_http_date("Thu, 01 Jan 1970 00:00:10 GMT");
+        assert_eq!(parsed, Some(UNIX_EPOCH + Duration::from_secs(10)));
+        assert_eq!(
+            parse_http_date("Sun, 29 Feb 2004 12:00:00 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_078_056_000))
+        );
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+
+    #[tokio::test]
+    async fn test_retries_retryable_statuses_until_success() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let replies = [
+                "HTTP/1.1 429 Too Many Requests\r\nretry-after-ms: 10\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                // Anthropic's overloaded status.
+                "HTTP/1.1 529 Overloaded\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok",
+            ];
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await.unwrap();
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+
+        let policy = RetryPolicy::default().with_base_delay(Duration::from_millis(1));
+        let res = send_with_retry(&policy, reqwest::Client::new().get(&url))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().await.unwrap(), "ok");
+        server.await.unwrap();
+
+        // With retries disabled, the retryable status is returned to the caller as is.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let res = send_with_retry(&RetryPolicy::none(), reqwest::Client::new().get(&url))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), 503);
+    }
+}
@@ -0,0 +1,276 @@
+//!
+//! Server-Sent Events Decoding
+//!
+//! A buffered SSE decoder shared by every SSE-based provider. Network chunks are
+//! buffered until a full line is available, so events split across reads (or several
+//! events packed into one read) are framed correctly. Handles `\n`, `\r\n` and `\r`
+//! line endings, multi-line `data:` fields, `event:`/`id:` fields, comments and the
+//! OpenAI-style `[DONE]` sentinel.
+
+use crate::traits::{CompletionStream, CompletionStreamChunk, ProviderError};
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::pin::Pin;
+
+/// Data payload that marks the end of an OpenAI-style stream.
+const DONE_SENTINEL: &str = "[DONE]";
+
+/// A single dispatched Server-Sent Event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    /// The `event:` field, if the server named the event.
+    pub event: Option<String>,
+    /// The `data:` lines of the event, joined with `\n`.
+    pub data: String,
+    /// The last `id:` field seen on this stream.
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// Returns true for the `[DONE]` sentinel that ends OpenAI-style streams.
+    pub fn is_done(&self) -> bool {
+        self.data == DONE_SENTINEL
+    }
+}
+
+/// Incremental decoder turning raw bytes into `SseEvent`s.
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    /// Bytes received but not yet terminated by a line ending.
+    buffer: Vec<u8>,
+    /// Event name for the event being assembled.
+    event: Option<String>,
+    /// Data lines for the event being assembled.
+    data: Vec<String>,
+    /// Last event id; persists across events per the SSE spec.
+    last_id: Option<String>,
+}
+
+impl SseDecoder {
+    /// Creates an empty decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds raw bytes into the decoder, returning every event completed by them.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        let mut start = 0;
+        let mut pos = 0;
+
+        while pos < self.buffer.len() {
+            match self.buffer[pos] {
+                b'\n' => {
+                    let line = String::from_utf8_lossy(&self.buffer[start..pos]).into_owned();
+                    events.extend(self.process_line(&line));
+                    pos += 1;
+                    start = pos;
+                }
+                b'\r' => {
+                    // A trailing `\r` may be the first half of a `\r\n` split across reads.
+                    if pos + 1 == self.buffer.len() {
+                        break;
+                    }
+                    let line = String::from_utf8_lossy(&self.buffer[start..pos]).into_owned();
+                    events.extend(self.process_line(&line));
+                    pos += if self.buffer[pos + 1] == b'\n' { 2 } else { 1 };
+                    start = pos;
+                }
+                _ => pos += 1,
+            }
+        }
+
+        self.buffer.drain(..start);
+        events
+    }
+
+    /// Flushes any unterminated line and pending event once the byte stream has ended.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let mut event = None;
+        if !self.buffer.is_empty() {
+            let line = String::from_utf8_lossy(&self.buffer).into_owned();
+            self.buffer.clear();
+            event = self.process_line(line.trim_end_matches('\r'));
+        }
+        event.or_else(|| self.dispatch())
+    }
+
+    /// Interprets one line of the stream, dispatching an event on blank lines.
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None; // Comment / keep-alive
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            _ => {} // `retry` and unknown fields are ignored
+        }
+        None
+    }
+
+    /// Emits the event assembled so far, if it carried any data.
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data).join("\n");
+        Some(SseEvent {
+            event,
+            data,
+            id: self.last_id.clone(),
+        })
+    }
+}
+
+/// Internal state threaded through `decode_sse_stream`.
+struct SseStreamState<F> {
+    bytes: Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>,
+    decoder: SseDecoder,
+    handler: F,
+    pending: VecDeque<Result<CompletionStreamChunk, ProviderError>>,
+    finished: bool,
+}
+
+/// Decodes a byte stream of Server-Sent Events into a `CompletionStream`.
+///
+/// `handler` is called once per event and returns the chunks for that event, so
+/// nothing is lost when a network read holds several events. The stream ends at
+/// `[DONE]`, at the end of the body, or after the first error.
+pub(crate) fn decode_sse_stream<S, F>(bytes: S, handler: F) -> CompletionStream
+where
+    S: Stream<Item = Result<Bytes, reqwest::Error>> + Send + 'static,
+    F: FnMut(SseEvent) -> Result<Vec<CompletionStreamChunk>, ProviderError> + Send + 'static,
+{
+    let state = SseStreamState {
+        bytes: Box::pin(bytes),
+        decoder: SseDecoder::new(),
+        handler,
+        pending: VecDeque::new(),
+        finished: false,
+    };
+
+    let chunk_stream = stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(item) = state.pending.pop_front() {
+                return Some((item, state));
+            }
+            if state.finished {
+                return None;
+            }
+
+            let events = match state.bytes.next().await {
+                Some(Ok(chunk)) => state.decoder.push(&chunk),
+                Some(Err(e)) => {
+                    state.finished = true;
+                    state.pending.push_back(Err(ProviderError::RequestError(e)));
+                    continue;
+                }
+                None => {
+                    state.finished = true;
+                    state.decoder.finish().into_iter().collect()
+                }
+            };
+
+            for event in events {
+                if event.is_done() {
+                    state.finished = true;
+                    break;
+                }
+                match (state.handler)(event) {
+                    Ok(chunks) => state.pending.extend(chunks.into_iter().map(Ok)),
+                    Err(e) => {
+                        state.finished = true;
+                        state.pending.push_back(Err(e));
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    Box::pin(chunk_stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_split_across_chunks() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b"event: message_start\nda").is_empty());
+        assert!(decoder.push(b"ta: {\"a\":").is_empty());
+        let events = decoder.push(b"1}\n\n");
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                event: Some("message_start".to_string()),
+                data: "{\"a\":1}".to_string(),
+                id: None
+            }]
+        );
+    }
+
+    #[test]
+    fn test_multiple_events_in_one_chunk_with_crlf() {
+        let mut decoder = SseDecoder::new();
+        let mut events = decoder.push(b": keep-alive\r\ndata: one\r\n\r\nid: 7\r\ndata: two\r");
+        events.extend(decoder.push(b"\n\r\ndata: [DONE]\r\n\r\n"));
+        let data: Vec<_> = events.iter().map(|e| e.data.as_str()).collect();
+        assert_eq!(data, vec!["one", "two", "[DONE]"]);
+        assert_eq!(events[1].id.as_deref(), Some("7"));
+        assert!(events[2].is_done());
+    }
+
+    #[test]
+    fn test_multi_line_data_and_finish() {
+        let mut decoder = SseDecoder::new();
+        assert!(
+            decoder
+                .push(b"data: first\ndata:second\n\ndata: tail")
+                .len()
+                == 1
+        );
+        let tail = decoder.finish().unwrap();
+        assert_eq!(tail.data, "tail");
+
+        let mut decoder = SseDecoder::new();
+        let events = decoder.push(b"data: first\ndata:second\n\n");
+        assert_eq!(events[0].data, "first\nsecond");
+    }
+
+    #[test]
+    fn test_decode_stream_stops_at_done() {
+        let chunks: Vec<Result<Bytes, reqwest::Error>> = vec![
+            Ok(Bytes::from_static(b"data: a\n\nda")),
+            Ok(Bytes::from_static(b"ta: b\n\ndata: [DONE]\n\ndata: c\n\n")),
+        ];
+        let stream = decode_sse_stream(stream::iter(chunks), |event| {
+            Ok(vec![CompletionStreamChunk {
+                delta: crate::traits::StreamContentDelta::Text(event.data),
+                usage: None,
+                finish_reason: None,
+            }])
+        });
+        let texts: Vec<String> = futures::executor::block_on(stream.collect::<Vec<_>>())
+            .into_iter()
+            .map(|chunk| match chunk.unwrap().delta {
+                crate::traits::StreamContentDelta::Text(t) => t,
+                other => panic!("unexpected delta: {:?}", other),
+            })
+            .collect();
+        assert_eq!(texts, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Model aliases.
+//!
+//! Provides `ModelRegistry`, an `LlmProvider` that owns several named providers and
+//! resolves logical model names such as "fast" or "smart" to a provider, a concrete
+//! model identifier and default request parameters. Switching the backend behind an
+//! alias is then a configuration change rather than a code change.
+
+use crate::config_file::{ConfigFile, ModelAlias, RequestDefaults};
+use crate::get_provider;
+use crate::traits::{
+    CompletionRequest, CompletionResponse, CompletionStream, LlmProvider, ModelInfo, ProviderError,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// What a model alias resolved to.
+#[derive(Clone)]
+pub struct ResolvedModel {
+    /// The alias that was resolved.
+    pub alias: String,
+    /// Name of the provider serving the alias.
+    pub provider_name: String,
+    /// The provider serving the alias.
+    pub provider: Arc<dyn LlmProvider>,
+    /// The provider's model identifier.
+    pub model: String,
+    /// The alias' parameters, with the registry defaults filled in.
+    pub params: RequestDefaults,
+}
+
+/// An `LlmProvider` that routes requests by model alias.
+///
+/// A request's `model` is looked up among the aliases (an empty model uses the default
+/// alias), its parameters are filled in from the alias and then from the registry
+/// defaults, and the request is sent to the alias' provider with the concrete model.
+/// Parameters set on the request itself always win.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{ChatMessage, CompletionRequest, LlmProvider, ModelRegistry};
+///
+/// # async fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// // llm.toml defines providers "ollama" and "openrouter" and the aliases "fast" and "smart".
+/// let client = ModelRegistry::load("llm.toml")?;
+///
+/// let request = CompletionRequest {
+///     model: "fast".to_string(),
+///     messages: vec![ChatMessage::user("Why is the sky blue?".to_string())],
+///     ..Default::default()
+/// };
+/// let response = client.completion(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ModelRegistry {
+    providers: HashMap<String, Arc<dyn LlmProvider>>,
+    models: HashMap<String, ModelAlias>,
+    defaults: RequestDefaults,
+    default_model: Option<String>,
+}
+
+impl ModelRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a registry from a configuration file, creating each provider with `get_provider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::ConfigError` if the file refers to undefined providers or
+    /// aliases, and any error `get_provider` returns for a provider's configuration.
+    pub fn from_config(file: &ConfigFile) -> Result<Self, ProviderError> {
+        file.validate()
+            .map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+
+        let mut registry = ModelRegistry::new().with_defaults(file.defaults.clone());
+        for (name, settings) in &file.providers {
+            registry =
+                registry.with_provider(name.clone(), get_provider(settings.to_llm_config())?);
+        }
+        for (alias, model) in &file.models {
+            registry = registry.with_model(alias.clone(), model.clone());
+        }
+        registry.default_model = file.default_model.clone();
+        Ok(registry)
+    }
+
+    /// Loads a configuration file with `ConfigFile::load` and builds a registry from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::ConfigError` if the file cannot be loaded, and the errors of
+    /// `from_config`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ProviderError> {
+        let file = ConfigFile::load(path).map_err(|e| ProviderError::ConfigError(e.to_string()))?;
+        Self::from_config(&file)
+    }
+
+    /// Adds or replaces the provider called `name` (builder style).
+    pub fn with_provider(mut self, name: String, provider: Arc<dyn LlmProvider>) -> Self {
+        self.providers.insert(name, provider);
+        self
+    }
+
+    /// Adds or replaces the alias `alias` (builder style).
+    pub fn with_model(mut self, alias: String, model: ModelAlias) -> Self {
+        self.models.insert(alias, model);
+        self
+    }
+
+    /// Sets the parameters applied to every request below the alias' own (builder style).
+    pub fn with_defaults(mut self, defaults: RequestDefaults) -> Self {
+        self.defaults = defaults;
+        self
+    }
+
+    /// Sets the alias used for requests with an empty `model` (builder style).
+    pub fn with_default_model(mut self, alias: String) -> Self {
+        self.default_model = Some(alias);
+        self
+    }
+
+    /// The provider called `name`, if defined.
+    pub fn provider(&self, name: &str) -> Option<&Arc<dyn LlmProvider>> {
+        self.providers.get(name)
+    }
+
+    /// The names of all defined aliases.
+    pub fn aliases(&self) -> impl Iterator<Item = &str> {
+        self.models.keys().map(String::as_str)
+    }
+
+    /// Resolves `model` (or the default alias, when `model` is empty) to its provider,
+    /// concrete model and parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::ConfigError` if the alias or its provider is not defined.
+    pub fn resolve(&self, model: &str) -> Result<ResolvedModel, ProviderError> {
+        let alias = match (model, &self.default_model) {
+            ("", Some(default_model)) => default_model.as_str(),
+            ("", None) => {
+                return Err(ProviderError::ConfigError(
+                    "Request has no model and no default model is set".to_string(),
+                ))
+            }
+            (alias, _) => alias,
+        };
+        let entry = self
+            .models
+            .get(alias)
+            .ok_or_else(|| ProviderError::ConfigError(format!("Unknown model alias: {}", alias)))?;
+        let provider = self.providers.get(&entry.provider).ok_or_else(|| {
+            ProviderError::ConfigError(format!(
+                "Model alias '{}' uses unknown provider '{}'",
+                alias, entry.provider
+            ))
+        })?;
+
+        let mut params = entry.params.clone();
+        params.fill_from(&self.defaults);
+        Ok(ResolvedModel {
+            alias: alias.to_string(),
+            provider_name: entry.provider.clone(),
+            provider: provider.clone(),
+            model: entry.model.clone(),
+            params,
+        })
+    }
+
+    /// Resolves the request's model and rewrites the request for the alias' provider.
+    fn prepare(
+        &self,
+        mut request: CompletionRequest,
+    ) -> Result<(Arc<dyn LlmProvider>, CompletionRequest), ProviderError> {
+        let resolved = self.resolve(&request.model)?;
+        resolved.params.apply(&mut request);
+        request.model = resolved.model;
+        Ok((resolved.provider, request))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ModelRegistry {
+    /// Sends the request to the provider behind its model alias.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let (provider, request) = self.prepare(request)?;
+        provider.completion(request).await
+    }
+
+    /// Opens the stream on the provider behind its model alias.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let (provider, request) = self.prepare(request)?;
+        provider.completion_stream(request).await
+    }
+
+    /// Lists the aliases, named after the provider and model they resolve to.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let mut models: Vec<ModelInfo> = self
+            .models
+            .iter()
+            .map(|(alias, entry)| ModelInfo {
+                id: alias.clone(),
+                name: Some(format!("{}/{}", entry.provider, entry.model)),
+                ..Default::default()
+            })
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+    use crate::traits::ChatMessage;
+
+    fn request(model: &str) -> CompletionRequest {
+        CompletionRequest {
+            model: model.to_string(),
+            messages: vec![ChatMessage::user("Hi".to_string())],
+            ..Default::default()
+        }
+    }
+
+    fn registry(local: Arc<MockProvider>, remote: Arc<MockProvider>) -> ModelRegistry {
+        let fast = RequestDefaults {
+            max_tokens: Some(256),
+            temperature: Some(0.1),
+            ..Default::default()
+        };
+        ModelRegistry::new()
+            .with_provider("ollama".to_string(), local)
+            .with_provider("openrouter".to_string(), remote)
+            .with_model(
+                "fast".to_string(),
+                ModelAlias::new("ollama".to_string(), "qwen3:4b".to_string()).with_params(fast),
+            )
+            .with_model(
+                "smart".to_string(),
+                ModelAlias::new(
+                    "openrouter".to_string(),
+                    "anthropic/claude-sonnet-4".to_string(),
+                ),
+            )
+            .with_defaults(RequestDefaults {
+                temperature: Some(0.7),
+                max_tokens: Some(1024),
+                ..Default::default()
+            })
+            .with_default_model("smart".to_string())
+    }
+
+    #[test]
+    fn test_resolves_aliases_to_provider_model_and_params() {
+        let (local, remote) = (MockProvider::new(), MockProvider::new());
+        let client = registry(local.clone(), remote.clone());
+
+        let response = futures::executor::block_on(client.completion(request("fast"))).unwrap();
+        assert_eq!(response.content(), Some("qwen3:4b"));
+        let sent = local.requests().pop().unwrap();
+        assert_eq!((sent.temperature, sent.max_tokens), (Some(0.1), Some(256)));
+
+        let mut explicit = request("");
+        explicit.temperature = Some(1.0);
+        let response = futures::executor::block_on(client.completion(explicit)).unwrap();
+        assert_eq!(response.content(), Some("anthropic/claude-sonnet-4"));
+        let sent = remote.requests().pop().unwrap();
+        assert_eq!((sent.temperature, sent.max_tokens), (Some(1.0), Some(1024)));
+    }
+
+    #[test]
+    fn test_unknown_alias_is_config_error() {
+        let client = registry(MockProvider::new(), MockProvider::new());
+        let result = futures::executor::block_on(client.completion(request("gpt-4o")));
+        assert!(
+            matches!(result, Err(ProviderError::ConfigError(message)) if message.contains("gpt-4o"))
+        );
+
+        let client = ModelRegistry::new();
+        assert!(matches!(
+            client.resolve(""),
+            Err(ProviderError::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_builds_providers_from_config_file() {
+        let file: ConfigFile = serde_json::from_str(
+            r#"{
+                "default_model": "fast",
+                "providers": { "local": { "provider": "ollama", "base_url": "http://gpu-1:11434" } },
+                "models": { "fast": { "provider": "local", "model": "qwen3:4b" } }
+            }"#,
+        )
+        .unwrap();
+        let client = ModelRegistry::from_config(&file).unwrap();
+
+        let resolved = client.resolve("").unwrap();
+        assert_eq!(
+            (resolved.alias.as_str(), resolved.provider_name.as_str()),
+            ("fast", "local")
+        );
+        assert_eq!(resolved.model, "qwen3:4b");
+        assert!(client.provider("local").is_some());
+
+        let models = futures::executor::block_on(client.list_models()).unwrap();
+        assert_eq!(models[0].name.as_deref(), Some("local/qwen3:4b"));
+    }
+}
@@ -0,0 +1,429 @@
+//! Load balancing.
+//!
+//! Provides `Router`, an `LlmProvider` that spreads requests over several deployments of
+//! the same logical model (several Ollama hosts, several API keys, ...). Deployments that
+//! fail with retryable errors are put in cooldown and the
+//! request is retried on another deployment.
+
+use crate::config::LlmConfig;
+use crate::get_provider;
+use crate::traits::{
+    CompletionRequest, CompletionResponse, CompletionStream, LlmProvider, ModelInfo, ProviderError,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How long a failing deployment is skipped by default.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How a `Router` picks the deployment for a request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RoutingStrategy {
+    /// Take turns over the deployments.
+    #[default]
+    RoundRobin,
+    /// Pick the deployment with the fewest requests (and open streams) in flight.
+    LeastInFlight,
+    /// Spread requests in proportion to each deployment's `weight`.
+    Weighted,
+}
+
+/// One deployment behind a `Router`.
+#[derive(Clone)]
+pub struct Deployment {
+    /// The provider serving this deployment.
+    pub provider: Arc<dyn LlmProvider>,
+    /// Model to use with this deployment instead of the request's model, if set.
+    pub model: Option<String>,
+    /// Relative share of requests under `RoutingStrategy::Weighted`.
+    pub weight: u32,
+}
+
+impl Deployment {
+    /// Creates a deployment for `provider` with weight 1.
+    pub fn new(provider: Arc<dyn LlmProvider>) -> Self {
+        Self {
+            provider,
+            model: None,
+            weight: 1,
+        }
+    }
+
+    /// Creates a deployment from a configuration through `get_provider`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_provider` for invalid configurations.
+    pub fn from_config(config: LlmConfig) -> Result<Self, ProviderError> {
+        Ok(Self::new(get_provider(config)?))
+    }
+
+    /// Sets the model override for this deployment (builder style).
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Sets the weight used by `RoutingStrategy::Weighted` (builder style).
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+}
+
+/// A deployment plus the health and load tracked for it.
+struct DeploymentState {
+    deployment: Deployment,
+    in_flight: AtomicUsize,
+    cooldown_until: Mutex<Option<Instant>>,
+}
+
+impl DeploymentState {
+    fn is_cooling_down(&self, now: Instant) -> bool {
+        self.cooldown_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some_and(|until| until > now)
+    }
+}
+
+/// Counts a request as in flight on a deployment until dropped.
+struct InFlight(Arc<DeploymentState>);
+
+impl InFlight {
+    fn new(state: Arc<DeploymentState>) -> Self {
+        state.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(state)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// An `LlmProvider` that load-balances over deployments of the same model.
+///
+/// Each request goes to the deployment picked by the `RoutingStrategy`. When that
+/// deployment fails with a retryable error (see `ProviderError::is_retryable`: rate limits,
+/// overload, server errors, timeouts and connection failures), it is skipped for the
+/// cooldown period and the request moves on to another deployment. When every deployment
+/// is cooling down, they are all tried anyway. Streams count as in flight until they are
+/// dropped, but only their initial connect moves to another deployment. `list_models`
+/// asks the first deployment that is not cooling down.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{Deployment, LlmConfig, Provider, Router, RoutingStrategy};
+///
+/// # fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// let hosts = ["http://gpu-1:11434", "http://gpu-2:11434"];
+/// let deployments = hosts
+///     .iter()
+///     .map(|host| Deployment::from_config(LlmConfig::new(Provider::Ollama).with_base_url(host.to_string())))
+///     .collect::<Result<Vec<_>, _>>()?;
+///
+/// let router = Router::new(RoutingStrategy::LeastInFlight, deployments);
+/// // router.completion(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Router {
+    deployments: Vec<Arc<DeploymentState>>,
+    strategy: RoutingStrategy,
+    cooldown: Duration,
+    next: AtomicUsize,
+    current_weights: Mutex<Vec<i64>>,
+}
+
+impl Router {
+    /// Creates a router over `deployments` using `strategy` and the default cooldown.
+    pub fn new(strategy: RoutingStrategy, deployments: Vec<Deployment>) -> Self {
+        let current_weights = Mutex::new(vec![0; deployments.len()]);
+        let deployments = deployments
+            .into_iter()
+            .map(|deployment| {
+                Arc::new(DeploymentState {
+                    deployment,
+                    in_flight: AtomicUsize::new(0),
+                    cooldown_until: Mutex::new(None),
+                })
+            })
+            .collect();
+        Self {
+            deployments,
+            strategy,
+            cooldown: DEFAULT_COOLDOWN,
+            next: AtomicUsize::new(0),
+            current_weights,
+        }
+    }
+
+    /// Creates a router with one deployment per configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of `get_provider` for the first invalid configuration.
+    pub fn from_configs(
+        strategy: RoutingStrategy,
+        configs: Vec<LlmConfig>,
+    ) -> Result<Self, ProviderError> {
+        let deployments = configs
+            .into_iter()
+            .map(Deployment::from_config)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(strategy, deployments))
+    }
+
+    /// Sets how long a failing deployment is skipped (builder style).
+    pub fn with_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Picks the next deployment among those not `tried` yet, preferring ones that are
+    /// not cooling down.
+    fn select(&self, tried: &[bool]) -> Option<usize> {
+        let now = Instant::now();
+        let untried: Vec<usize> = (0..self.deployments.len()).filter(|&i| !tried[i]).collect();
+        let healthy: Vec<usize> = untried
+            .iter()
+            .copied()
+            .filter(|&i| !self.deployments[i].is_cooling_down(now))
+            .collect();
+        let candidates = if healthy.is_empty() { untried } else { healthy };
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let len = self.deployments.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % len;
+        let turn = |i: usize| (i + len - start) % len;
+        match self.strategy {
+            RoutingStrategy::RoundRobin => candidates.into_iter().min_by_key(|&i| turn(i)),
+            RoutingStrategy::LeastInFlight => candidates.into_iter().min_by_key(|&i| {
+                (
+                    self.deployments[i].in_flight.load(Ordering::SeqCst),
+                    turn(i),
+                )
+            }),
+            RoutingStrategy::Weighted => {
+                // Smooth weighted round-robin: every candidate earns its weight, the richest
+                // one is picked and pays back the total.
+                let mut current = self
+                    .current_weights
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner);
+                let mut total = 0;
+                for &i in &candidates {
+                    let weight = i64::from(self.deployments[i].deployment.weight);
+                    current[i] += weight;
+                    total += weight;
+                }
+                let picked = candidates
+                    .into_iter()
+                    .max_by_key(|&i| (current[i], std::cmp::Reverse(i)))?;
+                current[picked] -= total;
+                Some(picked)
+            }
+        }
+    }
+
+    /// Calls `call` on selected deployments until one succeeds or fails with an error
+    /// that does not point at an unhealthy deployment.
+    async fn run<T, F, Fut>(
+        &self,
+        request: CompletionRequest,
+        call: F,
+    ) -> Result<(T, InFlight), ProviderError>
+    where
+        F: Fn(Arc<dyn LlmProvider>, CompletionRequest) -> Fut,
+        Fut: Future<Output = Result<T, ProviderError>>,
+    {
+        if self.deployments.is_empty() {
+            return Err(ProviderError::ConfigError(
+                "Router has no deployments".to_string(),
+            ));
+        }
+
+        let mut tried = vec![false; self.deployments.len()];
+        loop {
+            let index = self.select(&tried).expect("an untried deployment remains");
+            tried[index] = true;
+            let state = &self.deployments[index];
+
+            let mut request = request.clone();
+            if let Some(model) = &state.deployment.model {
+                request.model = model.clone();
+            }
+
+            let in_flight = InFlight::new(state.clone());
+            match call(state.deployment.provider.clone(), request).await {
+                Ok(value) => {
+                    *state
+                        .cooldown_until
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = None;
+                    return Ok((value, in_flight));
+                }
+                // Retryable errors say something about the deployment rather than the request.
+                Err(error) if error.is_retryable() => {
+                    let until = Instant::now() + self.cooldown;
+                    *state
+                        .cooldown_until
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner) = Some(until);
+                    if tried.iter().all(|&t| t) {
+                        return Err(error);
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for Router {
+    /// Sends the request to the deployment picked by the routing strategy.
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let (response, _in_flight) = self
+            .run(request, |provider, request| async move {
+                provider.completion(request).await
+            })
+            .await?;
+        Ok(response)
+    }
+
+    /// Opens the stream on the deployment picked by the routing strategy.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let (stream, in_flight) = self
+            .run(request, |provider, request| async move {
+                provider.completion_stream(request).await
+            })
+            .await?;
+        Ok(Box::pin(stream.map(move |chunk| {
+            let _ = &in_flight;
+            chunk
+        })))
+    }
+
+    /// Lists the models of the first deployment that is not cooling down (or of the first
+    /// deployment, when all are).
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        let now = Instant::now();
+        let state = self
+            .deployments
+            .iter()
+            .find(|state| !state.is_cooling_down(now))
+            .or_else(|| self.deployments.first())
+            .ok_or_else(|| ProviderError::ConfigError("Router has no deployments".to_string()))?;
+        state.deployment.provider.list_models().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+
+    fn served_by(router: &Router, requests: usize) -> String {
+        (0..requests)
+            .map(|_| {
+                let response =
+                    futures::executor::block_on(router.completion(CompletionRequest::default()))
+                        .unwrap();
+                response.content().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_and_weighted_spread() {
+        let deployments = || {
+            vec![
+                Deployment::new(MockProvider::replying("a")).with_weight(3),
+                Deployment::new(MockProvider::replying("b")),
+            ]
+        };
+
+        let router = Router::new(RoutingStrategy::RoundRobin, deployments());
+        assert_eq!(served_by(&router, 4), "abab");
+
+        let router = Router::new(RoutingStrategy::Weighted, deployments());
+        assert_eq!(served_by(&router, 8), "aabaaaba");
+    }
+
+    #[test]
+    fn test_least_in_flight_prefers_idle_deployment() {
+        let router = Router::new(
+            RoutingStrategy::LeastInFlight,
+            vec![
+                Deployment::new(MockProvider::replying("a")),
+                Deployment::new(MockProvider::replying("b")),
+            ],
+        );
+        let _busy = InFlight::new(router.deployments[0].clone());
+        assert_eq!(served_by(&router, 3), "bbb");
+    }
+
+    #[test]
+    fn test_unhealthy_deployment_cools_down() {
+        let failing = MockProvider::failing(503, "unavailable");
+        let healthy = MockProvider::replying("b");
+        let router = Router::new(
+            RoutingStrategy::RoundRobin,
+            vec![
+                Deployment::new(failing.clone()),
+                Deployment::new(healthy.clone()),
+            ],
+        );
+
+        assert_eq!(served_by(&router, 3), "bbb");
+        assert_eq!(failing.calls(), 1);
+
+        // Errors about the request itself are returned without trying elsewhere.
+        let router = Router::new(
+            RoutingStrategy::RoundRobin,
+            vec![
+                Deployment::new(MockProvider::failing(400, "bad request")),
+                Deployment::new(healthy.clone()),
+            ],
+        );
+        let result = futures::executor::block_on(router.completion(CompletionRequest::default()));
+        assert!(matches!(
+            result,
+            Err(ProviderError::ApiError { status: 400, .. })
+        ));
+    }
+
+    #[test]
+    fn test_list_models_uses_first_healthy_deployment() {
+        let router = Router::new(
+            RoutingStrategy::RoundRobin,
+            vec![
+                Deployment::new(MockProvider::replying("a")),
+                Deployment::new(MockProvider::replying("b")),
+            ],
+        );
+        let models = futures::executor::block_on(router.list_models()).unwrap();
+        assert_eq!(models[0].id, "a");
+
+        *router.deployments[0].cooldown_until.lock().unwrap() =
+            Some(Instant::now() + Duration::from_secs(60));
+        let models = futures::executor::block_on(router.list_models()).unwrap();
+        assert_eq!(models[0].id, "b");
+    }
+}
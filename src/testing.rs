@@ -0,0 +1,165 @@
+//! Test doubles shared by the unit tests.
+
+use crate::traits::{
+    CompletionChoice, CompletionRequest, CompletionResponse, CompletionStream,
+    CompletionStreamChunk, LlmProvider, ModelInfo, ProviderError, StreamContentDelta, TokenUsage,
+};
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// A configurable `LlmProvider` that records the requests it receives.
+///
+/// By default it answers with the requested model as content, which shows which model a
+/// wrapper sent the request with. Replies can instead be fixed, scripted or an API error.
+#[derive(Default)]
+pub(crate) struct MockProvider {
+    /// Replies used in order before falling back to `content`.
+    script: Mutex<VecDeque<String>>,
+    /// Fixed reply content; the requested model when unset.
+    content: Option<String>,
+    /// Status and message of the API error returned for every request.
+    error: Option<(u16, String)>,
+    /// Prompt tokens reported as usage.
+    usage: Option<u32>,
+    requests: Mutex<Vec<CompletionRequest>>,
+}
+
+impl MockProvider {
+    /// A provider answering with the requested model.
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// A provider always answering with `content`.
+    pub(crate) fn replying(content: &str) -> Arc<Self> {
+        Arc::new(Self {
+            content: Some(content.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// A provider answering with `replies` in order.
+    pub(crate) fn scripted(replies: &[&str]) -> Arc<Self> {
+        let script = replies.iter().map(|reply| reply.to_string()).collect();
+        Arc::new(Self {
+            script: Mutex::new(script),
+            ..Default::default()
+        })
+    }
+
+    /// A provider failing every request with an API error.
+    pub(crate) fn failing(status: u16, message: &str) -> Arc<Self> {
+        Arc::new(Self {
+            error: Some((status, message.to_string())),
+            ..Default::default()
+        })
+    }
+
+    /// A provider answering "ok" and reporting `prompt_tokens` of usage.
+    pub(crate) fn with_usage(prompt_tokens: u32) -> Arc<Self> {
+        Arc::new(Self {
+            content: Some("ok".to_string()),
+            usage: Some(prompt_tokens),
+            ..Default::default()
+        })
+    }
+
+    /// The requests received so far.
+    pub(crate) fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The models of the requests received so far.
+    pub(crate) fn models(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| request.model.clone())
+            .collect()
+    }
+
+    /// The number of requests received so far.
+    pub(crate) fn calls(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    /// Records `request` and returns the reply content and usage, or the configured error.
+    fn reply(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<(String, Option<TokenUsage>), ProviderError> {
+        let model = request.model.clone();
+        self.requests.lock().unwrap().push(request);
+        if let Some((status, message)) = &self.error {
+            return Err(ProviderError::api_error(
+                *status,
+                message.clone(),
+                Default::default(),
+            ));
+        }
+
+        let content = self
+            .script
+            .lock()
+            .unwrap()
+            .pop_front()
+            .or_else(|| self.content.clone())
+            .unwrap_or(model);
+        let usage = self.usage.map(|tokens| TokenUsage {
+            prompt_tokens: tokens,
+            completion_tokens: 0,
+            total_tokens: tokens,
+        });
+        Ok((content, usage))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError> {
+        let (content, usage) = self.reply(request)?;
+        let choice = CompletionChoice {
+            content: Some(content),
+            ..Default::default()
+        };
+        Ok(CompletionResponse {
+            choices: vec![choice],
+            usage,
+        })
+    }
+
+    /// Streams the reply as a single chunk carrying the usage.
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError> {
+        let (content, usage) = self.reply(request)?;
+        let chunk = CompletionStreamChunk {
+            delta: StreamContentDelta::Text(content),
+            usage,
+            finish_reason: None,
+        };
+        Ok(Box::pin(futures::stream::iter(vec![Ok(chunk)])))
+    }
+
+    /// Lists a single model named after the fixed reply content, or fails like `completion`.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        if let Some((status, message)) = &self.error {
+            return Err(ProviderError::api_error(
+                *status,
+                message.clone(),
+                Default::default(),
+            ));
+        }
+        let id = self.content.clone().unwrap_or_else(|| "mock".to_string());
+        Ok(vec![ModelInfo {
+            id,
+            ..Default::default()
+        }])
+    }
+}
@@ -1,7 +1,12 @@
+//! Tool registry utilities.
+//!
+//! Provides a registry for tool definitions and their executors, plus a global
+//! registry used by the `merco_tool` procedural macro.
+
 use crate::traits::{Tool, ToolCallFunction};
+use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use lazy_static::lazy_static;
 
 /// Represents a tool function that can be executed with JSON arguments
 pub type ToolExecutor = Arc<dyn Fn(&str) -> Result<String, String> + Send + Sync>;
@@ -43,9 +48,16 @@ impl ToolRegistry {
     }
 }
 
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Global registry singleton
 lazy_static! {
-    static ref GLOBAL_REGISTRY: Arc<Mutex<ToolRegistry>> = Arc::new(Mutex::new(ToolRegistry::new()));
+    static ref GLOBAL_REGISTRY: Arc<Mutex<ToolRegistry>> =
+        Arc::new(Mutex::new(ToolRegistry::new()));
 }
 
 /// Register a tool in the global registry
@@ -59,7 +71,11 @@ pub fn register_tool(tool: Tool, executor: ToolExecutor) {
 
 /// Helper function for procedural macro to register a tool with tool definition and executor
 #[doc(hidden)]
-pub fn __register_macro_tool(tool_name: &str, tool_definition: Tool, executor_fn: impl Fn(&str) -> Result<String, String> + Send + Sync + 'static) {
+pub fn __register_macro_tool(
+    _tool_name: &str,
+    tool_definition: Tool,
+    executor_fn: impl Fn(&str) -> Result<String, String> + Send + Sync + 'static,
+) {
     register_tool(tool_definition, Arc::new(executor_fn));
 }
 
@@ -85,7 +101,10 @@ pub fn get_tools_by_names(names: &[&str]) -> Vec<Tool> {
     let registry = match GLOBAL_REGISTRY.lock() {
         Ok(reg) => reg,
         Err(e) => {
-            eprintln!("[Tool Registry] Failed to lock registry for getting tools by name: {}", e);
+            eprintln!(
+                "[Tool Registry] Failed to lock registry for getting tools by name: {}",
+                e
+            );
             return Vec::new(); // Return empty list on lock failure
         }
     };
@@ -116,7 +135,7 @@ mod tests {
     #[test]
     fn test_tool_registry() {
         let mut registry = ToolRegistry::new();
-        
+
         // Create a simple addition tool
         let add_tool = Tool {
             name: "add".to_string(),
@@ -132,7 +151,7 @@ mod tests {
                 required: Some(vec!["a".to_string(), "b".to_string()]),
             },
         };
-        
+
         // Create executor function
         let add_executor: ToolExecutor = Arc::new(|args| {
             let parsed: Result<serde_json::Value, _> = serde_json::from_str(args);
@@ -145,20 +164,20 @@ mod tests {
                 Err(e) => Err(format!("Failed to parse arguments: {}", e)),
             }
         });
-        
+
         // Register the tool
         registry.register(add_tool.clone(), add_executor);
-        
+
         // Check that the tool was registered
         assert_eq!(registry.get_tools().len(), 1);
         assert_eq!(registry.get_tools()[0].name, "add");
-        
+
         // Execute the tool
         let result = registry.execute_tool("add", r#"{"a": 5, "b": 3}"#);
         assert_eq!(result, Ok("8".to_string()));
-        
+
         // Try executing a non-existent tool
         let error = registry.execute_tool("multiply", r#"{"a": 5, "b": 3}"#);
         assert!(error.is_err());
     }
-} 
\ No newline at end of file
+}
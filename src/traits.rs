@@ -1,8 +1,12 @@
+//! Core traits and shared data structures used by all LLM providers.
+
 use async_trait::async_trait;
 use futures::stream::Stream; // Requires the `futures` crate
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue; // For JSON Schema representation
+use std::collections::HashMap;
 use std::pin::Pin;
+use std::time::Duration;
 use thiserror::Error;
 
 // --- Tool Calling Structures ---
@@ -33,7 +37,7 @@ pub struct JsonSchema {
 // --- Request/Response Structures ---
 
 /// Represents a request to an LLM provider for chat completion.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionRequest {
     /// A list of messages comprising the conversation history.
     pub messages: Vec<ChatMessage>,
@@ -45,15 +49,179 @@ pub struct CompletionRequest {
     /// Maximum number of tokens to generate.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
+    /// Nucleus sampling: only tokens within the top `top_p` probability mass are considered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Only the `top_k` most likely tokens are considered at each step.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Sequences that stop generation when produced.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Seed for deterministic sampling, where supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Penalizes tokens that have already appeared at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Penalizes tokens in proportion to how often they have appeared.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Bias added to the logits of the given token ids.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<u32, f32>>,
+    /// Number of choices to generate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
     /// A list of tools the model may call.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
-    // Consider adding tool_choice option later.
+    /// Controls whether and which tool the model calls. Defaults to `Auto` when tools are present.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Constrains the format of the model's text output (e.g. JSON or a JSON Schema).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Controls the model's reasoning (thinking), for models that support it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<Reasoning>,
+    /// Extra top-level fields merged into the provider's JSON request body, for provider
+    /// parameters not modelled here (e.g. OpenRouter `provider`, Ollama `keep_alive`).
+    /// Fields set here replace those generated from the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_body: Option<serde_json::Map<String, JsonValue>>,
+    /// Extra HTTP headers sent with the request, replacing provider headers of the same name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_headers: Option<HashMap<String, String>>,
 }
 
 impl CompletionRequest {
-    pub fn new(messages: Vec<ChatMessage>, model: String, temperature: Option<f32>, max_tokens: Option<u32>, tools: Option<Vec<Tool>>) -> Self {
-        Self { messages, model, temperature, max_tokens, tools }
+    /// Creates a new completion request.
+    pub fn new(
+        messages: Vec<ChatMessage>,
+        model: String,
+        temperature: Option<f32>,
+        max_tokens: Option<u32>,
+        tools: Option<Vec<Tool>>,
+    ) -> Self {
+        Self {
+            messages,
+            model,
+            temperature,
+            max_tokens,
+            tools,
+            ..Default::default()
+        }
+    }
+}
+
+/// Controls how the model uses the tools provided in a `CompletionRequest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools.
+    Auto,
+    /// The model must not call any tools.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named function.
+    Function {
+        /// The name of the function to call.
+        name: String,
+    },
+}
+
+/// Requested format of the model's text output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Plain text (the provider default).
+    Text,
+    /// Any syntactically valid JSON object.
+    JsonObject,
+    /// JSON matching the given JSON Schema.
+    JsonSchema {
+        /// A name for the schema, required by some providers.
+        name: String,
+        /// The JSON Schema the output must match.
+        schema: JsonValue,
+        /// Whether the provider should enforce the schema strictly, where supported.
+        #[serde(default)]
+        strict: bool,
+    },
+}
+
+impl ResponseFormat {
+    /// Creates a strict JSON Schema response format.
+    pub fn json_schema(name: impl Into<String>, schema: JsonValue) -> Self {
+        ResponseFormat::JsonSchema {
+            name: name.into(),
+            schema,
+            strict: true,
+        }
+    }
+}
+
+/// Reasoning (thinking) controls for a `CompletionRequest`.
+///
+/// Providers map this to their native parameter: OpenAI `reasoning_effort`, OpenRouter
+/// `reasoning`, Ollama `think` and Anthropic `thinking`. Forms a provider cannot express
+/// are rejected with `ProviderError::Unsupported`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reasoning {
+    /// Turn reasoning off.
+    Disabled,
+    /// Reason with the given relative effort.
+    Effort(ReasoningEffort),
+    /// Reason with at most this many tokens.
+    BudgetTokens(u32),
+}
+
+/// A reasoning block as returned by the provider, kept verbatim so it can be sent back.
+///
+/// Anthropic requires the previous assistant turn's thinking blocks, signatures included,
+/// when a conversation with thinking enabled continues after a tool call. Keep them on the
+/// assistant `ChatMessage` (`CompletionChoice::to_message` does this).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReasoningBlock {
+    /// Reasoning text with the signature that lets the provider verify it.
+    Thinking {
+        /// The reasoning text.
+        thinking: String,
+        /// The provider's signature over the text.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// Reasoning the provider returned encrypted.
+    Redacted {
+        /// The encrypted reasoning, passed back unchanged.
+        data: String,
+    },
+}
+
+/// Relative reasoning effort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    /// Minimal reasoning, favouring speed.
+    Low,
+    /// Balanced reasoning.
+    Medium,
+    /// Extensive reasoning, favouring quality.
+    High,
+}
+
+impl ReasoningEffort {
+    /// Returns the lowercase name used by provider APIs.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReasoningEffort::Low => "low",
+            ReasoningEffort::Medium => "medium",
+            ReasoningEffort::High => "high",
+        }
     }
 }
 
@@ -75,13 +243,18 @@ pub enum ChatMessageRole {
 }
 
 /// Represents a single message in a chat conversation.
+///
+/// Build messages with the constructors (`ChatMessage::user`, `assistant`, ...) rather
+/// than struct literals: the struct is `#[non_exhaustive]` so fields can be added
+/// without breaking callers. Fields stay public for reading and adjusting.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct ChatMessage {
     /// The role of the message sender (e.g., "system", "user", "assistant", "tool").
     pub role: ChatMessageRole,
-    /// The text content of the message. Can be None for assistant messages requesting tool calls
-    /// or for tool messages providing results.
-    pub content: Option<String>,
+    /// The content of the message: plain text, or a list of parts (text and images).
+    /// Can be None for assistant messages requesting tool calls.
+    pub content: Option<MessageContent>,
     /// A list of tool calls requested by the assistant.
     /// Present only for `assistant` role messages when tools are called.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -90,31 +263,197 @@ pub struct ChatMessage {
     /// Present only for `tool` role messages.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Reasoning blocks of an assistant message, sent back to providers that require them
+    /// (Anthropic, when thinking is enabled and tools are used).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasoning_blocks: Vec<ReasoningBlock>,
 }
 
 impl ChatMessage {
-    pub fn new(role: ChatMessageRole, content: Option<String>, tool_calls: Option<Vec<ToolCallRequest>>, tool_call_id: Option<String>) -> Self {
-        Self { role, content, tool_calls, tool_call_id }
+    /// Creates a new chat message from its raw parts.
+    pub fn new(
+        role: ChatMessageRole,
+        content: Option<String>,
+        tool_calls: Option<Vec<ToolCallRequest>>,
+        tool_call_id: Option<String>,
+    ) -> Self {
+        Self {
+            role,
+            content: content.map(MessageContent::Text),
+            tool_calls,
+            tool_call_id,
+            reasoning_blocks: Vec::new(),
+        }
     }
-    
-    // Helper for creating a user message
+
+    /// Helper for creating a user message.
     pub fn user(content: String) -> Self {
-        Self { role: ChatMessageRole::User, content: Some(content), tool_calls: None, tool_call_id: None }
+        Self {
+            role: ChatMessageRole::User,
+            content: Some(MessageContent::Text(content)),
+            tool_calls: None,
+            tool_call_id: None,
+            reasoning_blocks: Vec::new(),
+        }
+    }
+
+    /// Helper for creating a user message made of several parts, e.g. text and images.
+    pub fn user_parts(parts: Vec<ContentPart>) -> Self {
+        Self {
+            role: ChatMessageRole::User,
+            content: Some(MessageContent::Parts(parts)),
+            tool_calls: None,
+            tool_call_id: None,
+            reasoning_blocks: Vec::new(),
+        }
     }
-    
-    // Helper for creating a system message
+
+    /// Helper for creating a system message.
     pub fn system(content: String) -> Self {
-        Self { role: ChatMessageRole::System, content: Some(content), tool_calls: None, tool_call_id: None }
+        Self {
+            role: ChatMessageRole::System,
+            content: Some(MessageContent::Text(content)),
+            tool_calls: None,
+            tool_call_id: None,
+            reasoning_blocks: Vec::new(),
+        }
     }
 
-    // Helper for creating an assistant message
+    /// Helper for creating an assistant message.
     pub fn assistant(content: Option<String>, tool_calls: Option<Vec<ToolCallRequest>>) -> Self {
-         Self { role: ChatMessageRole::Assistant, content, tool_calls, tool_call_id: None }
+        Self {
+            role: ChatMessageRole::Assistant,
+            content: content.map(MessageContent::Text),
+            tool_calls,
+            tool_call_id: None,
+            reasoning_blocks: Vec::new(),
+        }
     }
 
-    // Helper for creating a tool result message
+    /// Helper for creating a tool result message.
     pub fn tool_result(tool_call_id: String, content: String) -> Self {
-         Self { role: ChatMessageRole::Tool, content: Some(content), tool_calls: None, tool_call_id: Some(tool_call_id) }
+        Self {
+            role: ChatMessageRole::Tool,
+            content: Some(MessageContent::Text(content)),
+            tool_calls: None,
+            tool_call_id: Some(tool_call_id),
+            reasoning_blocks: Vec::new(),
+        }
+    }
+
+    /// Returns the text of the message, joining text parts with newlines and skipping images.
+    pub fn text(&self) -> Option<String> {
+        self.content.as_ref().map(MessageContent::text)
+    }
+}
+
+/// The content of a `ChatMessage`: a plain string or a list of typed parts.
+/// Serializes as a JSON string or array respectively.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// Plain text content.
+    Text(String),
+    /// A list of content parts, e.g. text followed by one or more images.
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// Returns the text content, joining text parts with newlines and skipping images.
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// Returns the parts of the content; plain text becomes a single text part.
+    pub fn parts(&self) -> Vec<ContentPart> {
+        match self {
+            MessageContent::Text(text) => vec![ContentPart::Text { text: text.clone() }],
+            MessageContent::Parts(parts) => parts.clone(),
+        }
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        MessageContent::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        MessageContent::Parts(parts)
+    }
+}
+
+/// A single part of a multimodal message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// A piece of text.
+    Text {
+        /// The text.
+        text: String,
+    },
+    /// An image referenced by URL (`https://...` or a `data:` URL).
+    ImageUrl {
+        /// The image URL.
+        url: String,
+    },
+    /// An image given inline as base64-encoded data.
+    ImageBase64 {
+        /// The image's media type, e.g. `image/png`.
+        media_type: String,
+        /// The base64-encoded image bytes.
+        data: String,
+    },
+}
+
+impl ContentPart {
+    /// Creates a text part.
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// Creates an image part referenced by URL.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl { url: url.into() }
+    }
+
+    /// Creates an inline base64 image part.
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        ContentPart::ImageBase64 {
+            media_type: media_type.into(),
+            data: data.into(),
+        }
+    }
+
+    /// Returns `(media_type, data)` for inline base64 images, including base64 `data:` URLs.
+    pub(crate) fn base64_image(&self) -> Option<(&str, &str)> {
+        match self {
+            ContentPart::ImageBase64 { media_type, data } => Some((media_type, data)),
+            ContentPart::ImageUrl { url } => {
+                let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+                let media_type = header.strip_suffix(";base64")?;
+                Some((media_type, data))
+            }
+            ContentPart::Text { .. } => None,
+        }
     }
 }
 
@@ -131,6 +470,7 @@ pub struct ToolCallRequest {
 }
 
 impl ToolCallRequest {
+    /// Creates a new function-type tool call request.
     pub fn new_function_call(id: String, function: ToolCallFunction) -> Self {
         Self {
             id,
@@ -149,28 +489,193 @@ pub struct ToolCallFunction {
     pub arguments: String,
 }
 
-/// Represents the kind of result returned by a completion: either a message or tool calls.
+/// An either/or view of a completion choice: a message, or tool calls.
+///
+/// Returned by `CompletionChoice::kind` for callers that only branch on whether tools
+/// were called. Any text accompanying tool calls is available on the choice itself.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum CompletionKind {
     /// The LLM generated a text message.
-    Message { content: String },
+    Message {
+        /// The text content of the message.
+        content: String,
+    },
     /// The LLM requested one or more tool calls.
-    ToolCall { tool_calls: Vec<ToolCallRequest> },
+    ToolCall {
+        /// The tool calls requested by the model.
+        tool_calls: Vec<ToolCallRequest>,
+    },
+}
+
+/// Why the model stopped generating tokens, normalized across providers.
+///
+/// Serializes to the OpenAI vocabulary (`"stop"`, `"length"`, `"tool_calls"`,
+/// `"content_filter"`) plus `"stop_sequence"`; any other value is kept verbatim in `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum FinishReason {
+    /// The model finished its turn naturally.
+    Stop,
+    /// The output was truncated by `max_tokens` or the context window.
+    Length,
+    /// The model stopped to call one or more tools.
+    ToolCalls,
+    /// The output was withheld or cut short by a content filter or refusal.
+    ContentFilter,
+    /// One of the requested `stop` sequences was produced.
+    StopSequence,
+    /// A provider-specific reason with no normalized equivalent.
+    Other(String),
+}
+
+impl FinishReason {
+    /// Returns the normalized string form of the reason.
+    pub fn as_str(&self) -> &str {
+        match self {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::ToolCalls => "tool_calls",
+            FinishReason::ContentFilter => "content_filter",
+            FinishReason::StopSequence => "stop_sequence",
+            FinishReason::Other(reason) => reason,
+        }
+    }
+}
+
+impl From<&str> for FinishReason {
+    fn from(reason: &str) -> Self {
+        match reason {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            "tool_calls" | "function_call" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            "stop_sequence" => FinishReason::StopSequence,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for FinishReason {
+    fn from(reason: String) -> Self {
+        FinishReason::from(reason.as_str())
+    }
+}
+
+impl From<FinishReason> for String {
+    fn from(reason: FinishReason) -> Self {
+        match reason {
+            FinishReason::Other(reason) => reason,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for FinishReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One of the alternative completions generated for a request.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionChoice {
+    /// The position of this choice in the response.
+    pub index: u32,
+    /// The text generated by the model, including any text emitted alongside tool calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// The tool calls requested by the model.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallRequest>,
+    /// The model's refusal message, if it declined to answer (where supported).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    /// The model's reasoning (thinking) text, for reasoning models that expose it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// The provider's reasoning blocks, to keep in the conversation history (see `ReasoningBlock`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasoning_blocks: Vec<ReasoningBlock>,
+    /// The reason the model stopped generating tokens for this choice (if available).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl CompletionChoice {
+    /// Returns `ToolCall` if the model requested any tools, otherwise `Message`.
+    pub fn kind(&self) -> CompletionKind {
+        if self.tool_calls.is_empty() {
+            CompletionKind::Message {
+                content: self.content.clone().unwrap_or_default(),
+            }
+        } else {
+            CompletionKind::ToolCall {
+                tool_calls: self.tool_calls.clone(),
+            }
+        }
+    }
+
+    /// Converts the choice into an assistant message, for appending to the conversation.
+    pub fn to_message(&self) -> ChatMessage {
+        let tool_calls = if self.tool_calls.is_empty() {
+            None
+        } else {
+            Some(self.tool_calls.clone())
+        };
+        ChatMessage {
+            reasoning_blocks: self.reasoning_blocks.clone(),
+            ..ChatMessage::assistant(self.content.clone(), tool_calls)
+        }
+    }
 }
 
 /// Represents the complete response from a non-streaming LLM completion request.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionResponse {
-    /// The kind of completion result (message or tool calls).
-    #[serde(flatten)]
-    pub kind: CompletionKind,
+    /// The generated choices; a single one unless the request set `n`.
+    pub choices: Vec<CompletionChoice>,
     /// Token usage information for the request (if available).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub usage: Option<TokenUsage>,
-    /// The reason the model stopped generating tokens (if available).
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub finish_reason: Option<String>,
+}
+
+impl CompletionResponse {
+    /// Returns the first choice, if any.
+    pub fn first_choice(&self) -> Option<&CompletionChoice> {
+        self.choices.first()
+    }
+
+    /// Returns the text of the first choice.
+    pub fn content(&self) -> Option<&str> {
+        self.first_choice()?.content.as_deref()
+    }
+
+    /// Returns the reasoning text of the first choice.
+    pub fn reasoning(&self) -> Option<&str> {
+        self.first_choice()?.reasoning.as_deref()
+    }
+
+    /// Returns the tool calls of the first choice.
+    pub fn tool_calls(&self) -> &[ToolCallRequest] {
+        self.first_choice()
+            .map(|choice| choice.tool_calls.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the finish reason of the first choice.
+    pub fn finish_reason(&self) -> Option<&FinishReason> {
+        self.first_choice()?.finish_reason.as_ref()
+    }
+
+    /// Returns the either/or view of the first choice.
+    pub fn kind(&self) -> CompletionKind {
+        self.first_choice()
+            .map(CompletionChoice::kind)
+            .unwrap_or(CompletionKind::Message {
+                content: String::new(),
+            })
+    }
 }
 
 /// Represents the kind of content delta in a streaming response chunk.
@@ -182,6 +687,13 @@ pub enum StreamContentDelta {
     /// Incremental information about tool calls being generated.
     #[serde(rename = "tool_calls")]
     ToolCallDelta(Vec<ToolCallStreamDelta>),
+    /// A chunk of the model's reasoning (thinking) text.
+    #[serde(rename = "reasoning")]
+    Reasoning(String),
+    /// A complete reasoning block, emitted once it ends so it can be kept in the history.
+    /// Its text was already streamed as `Reasoning` chunks.
+    #[serde(rename = "reasoning_block")]
+    ReasoningBlock(ReasoningBlock),
 }
 
 /// Represents incremental information about a single tool call within a stream.
@@ -214,7 +726,7 @@ pub struct CompletionStreamChunk {
     pub usage: Option<TokenUsage>,
     /// The reason the model stopped (usually only present in the final chunk, if at all).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub finish_reason: Option<String>,
+    pub finish_reason: Option<FinishReason>,
 }
 
 /// Represents token usage statistics for a completion request.
@@ -228,6 +740,37 @@ pub struct TokenUsage {
     pub total_tokens: u32,
 }
 
+/// Describes a model offered by a provider. Fields are `None` when the provider does not report them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    /// The identifier to use as `CompletionRequest.model`.
+    pub id: String,
+    /// A human-readable name for the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The maximum number of tokens in the context window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// Whether the model supports tool calling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    /// Whether the model accepts image input.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    /// Price of the model's tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPricing>,
+}
+
+/// Token prices of a model, in USD per token.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    /// Price per prompt (input) token.
+    pub prompt: f64,
+    /// Price per completion (output) token.
+    pub completion: f64,
+}
+
 /// Errors that can occur when interacting with LLM providers.
 #[derive(Error, Debug)]
 pub enum ProviderError {
@@ -235,8 +778,18 @@ pub enum ProviderError {
     #[error("API request failed: {0}")]
     RequestError(#[from] reqwest::Error),
     /// The API returned an error response (e.g., 4xx, 5xx).
+    /// Build it with `ProviderError::api_error` so `kind` is classified consistently.
     #[error("API response error: {status}: {message}")]
-    ApiError { status: u16, message: String },
+    ApiError {
+        /// The HTTP status code returned by the API.
+        status: u16,
+        /// The error message extracted from the response body.
+        message: String,
+        /// What went wrong, classified from the status, error code and message.
+        kind: ErrorKind,
+        /// Provider error fields, request id and raw body.
+        details: Box<ApiErrorDetails>,
+    },
     /// Failed to parse the JSON response from the API.
     #[error("Failed to parse API response: {0}")]
     ParseError(#[from] serde_json::Error),
@@ -252,6 +805,14 @@ pub enum ProviderError {
     /// Error related to the format or processing of tool use/calls.
     #[error("Tool use response format error: {0}")]
     ToolFormatError(String),
+    /// A rate limit was hit; `retry_after` says how long to wait, when known.
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        /// Which limit was hit.
+        message: String,
+        /// How long to wait before the request can be sent again.
+        retry_after: Option<Duration>,
+    },
     /// The requested operation is not supported by the provider implementation.
     #[error("Unsupported operation: {0}")]
     Unsupported(String),
@@ -260,6 +821,172 @@ pub enum ProviderError {
     Unexpected(String),
 }
 
+/// Broad classification of a `ProviderError`, see `ProviderError::kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The API key is missing, invalid or lacks permission (401/403).
+    Authentication,
+    /// A provider or client-side rate limit was hit (429).
+    RateLimited,
+    /// The prompt does not fit in the model's context window.
+    ContextLengthExceeded,
+    /// The request or completion was blocked by a content filter.
+    ContentFiltered,
+    /// The requested model does not exist or is not available.
+    ModelNotFound,
+    /// The provider is temporarily overloaded (503, Anthropic's 529).
+    Overloaded,
+    /// The request timed out, on the client or at a gateway (408/504).
+    Timeout,
+    /// The connection failed before a response arrived.
+    Connection,
+    /// The request was rejected as malformed (other 4xx).
+    InvalidRequest,
+    /// The provider failed with another server error (5xx), or a 409 conflict such as
+    /// OpenAI's lock timeouts, which clears when the request is sent again.
+    Server,
+    /// Anything else, including errors raised by this crate.
+    Other,
+}
+
+impl ErrorKind {
+    /// Classifies an API error response from its status, provider error code/type and message.
+    pub fn classify(status: u16, message: &str, details: &ApiErrorDetails) -> Self {
+        const CONTEXT_LENGTH_MARKERS: [&str; 5] = [
+            "context_length_exceeded",
+            "context length",
+            "context window",
+            "prompt is too long",
+            "too many tokens",
+        ];
+        const CONTENT_FILTER_MARKERS: [&str; 4] = [
+            "content_filter",
+            "content_policy_violation",
+            "content management policy",
+            "content filter",
+        ];
+
+        let code = details.code.as_deref().unwrap_or_default();
+        let error_type = details.error_type.as_deref().unwrap_or_default();
+        let message = message.to_lowercase();
+        let mentions = |markers: &[&str]| {
+            markers
+                .iter()
+                .any(|marker| code == *marker || error_type == *marker || message.contains(marker))
+        };
+
+        match status {
+            401 | 403 => ErrorKind::Authentication,
+            // OpenAI reports an exhausted quota as a 429 that will not clear by waiting.
+            429 if code == "insufficient_quota" => ErrorKind::Other,
+            429 => ErrorKind::RateLimited,
+            408 | 504 => ErrorKind::Timeout,
+            503 | 529 => ErrorKind::Overloaded,
+            409 => ErrorKind::Server,
+            _ if error_type == "overloaded_error" => ErrorKind::Overloaded,
+            400 | 413 | 422 if mentions(&CONTEXT_LENGTH_MARKERS) => {
+                ErrorKind::ContextLengthExceeded
+            }
+            400..=499 if mentions(&CONTENT_FILTER_MARKERS) => ErrorKind::ContentFiltered,
+            404 if code == "model_not_found" || message.contains("model") => {
+                ErrorKind::ModelNotFound
+            }
+            400..=499 => ErrorKind::InvalidRequest,
+            500..=599 => ErrorKind::Server,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Returns true if the same request may succeed when sent again later.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::RateLimited
+                | ErrorKind::Overloaded
+                | ErrorKind::Timeout
+                | ErrorKind::Connection
+                | ErrorKind::Server
+        )
+    }
+}
+
+/// Extra information about an API error response, kept for debugging and support tickets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApiErrorDetails {
+    /// The provider's error code, e.g. OpenAI's `context_length_exceeded`.
+    pub code: Option<String>,
+    /// The provider's error type, e.g. Anthropic's `overloaded_error`.
+    pub error_type: Option<String>,
+    /// The request parameter the error refers to, if reported.
+    pub param: Option<String>,
+    /// The provider's request id (`x-request-id` / `request-id` header).
+    pub request_id: Option<String>,
+    /// How long the provider asked to wait (`Retry-After` and rate limit headers).
+    pub retry_after: Option<Duration>,
+    /// The raw response body.
+    pub body: Option<String>,
+}
+
+impl ProviderError {
+    /// Builds a `ProviderError::ApiError`, classifying its kind from the response.
+    pub fn api_error(status: u16, message: String, details: ApiErrorDetails) -> Self {
+        let kind = ErrorKind::classify(status, &message, &details);
+        ProviderError::ApiError {
+            status,
+            message,
+            kind,
+            details: Box::new(details),
+        }
+    }
+
+    /// Classifies the error. Errors raised by this crate itself are `ErrorKind::Other`,
+    /// except client-side rate limits.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ProviderError::ApiError { kind, .. } => *kind,
+            ProviderError::RateLimited { .. } => ErrorKind::RateLimited,
+            ProviderError::RequestError(e) if e.is_timeout() => ErrorKind::Timeout,
+            ProviderError::RequestError(e)
+                if e.is_connect() || (e.is_request() && !e.is_builder()) =>
+            {
+                ErrorKind::Connection
+            }
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Returns true if the same request may succeed when sent again later
+    /// (rate limits, overload, timeouts, connection failures and server errors).
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+
+    /// How long the provider (or the client-side limiter) asked to wait, if it said.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ProviderError::ApiError { details, .. } => details.retry_after,
+            ProviderError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// The provider's request id for API errors, to quote in support tickets.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            ProviderError::ApiError { details, .. } => details.request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The raw response body for API errors.
+    pub fn body(&self) -> Option<&str> {
+        match self {
+            ProviderError::ApiError { details, .. } => details.body.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 /// Type alias for the stream of completion chunks.
 /// Uses dynamic dispatch (`dyn Stream`) and requires `Send` for async compatibility.
 pub type CompletionStream =
@@ -272,11 +999,68 @@ pub trait LlmProvider: Send + Sync {
     ///
     /// Takes a `CompletionRequest` and returns a `CompletionResponse`, which might contain
     /// either a text message or a request to call tools.
-    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse, ProviderError>;
+    async fn completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse, ProviderError>;
 
     /// Generates a streaming completion response.
     ///
     /// Takes a `CompletionRequest` and returns a stream (`CompletionStream`) that yields
     /// `CompletionStreamChunk` results.
-    async fn completion_stream(&self, request: CompletionRequest) -> Result<CompletionStream, ProviderError>;
-} 
\ No newline at end of file
+    async fn completion_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, ProviderError>;
+
+    /// Lists the models available from the provider, with whatever metadata it reports.
+    ///
+    /// The default implementation returns `ProviderError::Unsupported`.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>, ProviderError> {
+        Err(ProviderError::Unsupported(
+            "Model listing is not supported by this provider".to_string(),
+        ))
+    }
+}
+
+// --- Embeddings ---
+
+/// Represents a request for the embeddings of a batch of inputs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    /// The embedding model identifier.
+    pub model: String,
+    /// The texts to embed.
+    pub input: Vec<String>,
+    /// Number of dimensions to return, for models that support shortening embeddings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+}
+
+impl EmbeddingRequest {
+    /// Creates a new embedding request for the given inputs.
+    pub fn new(model: String, input: Vec<String>) -> Self {
+        Self {
+            model,
+            input,
+            dimensions: None,
+        }
+    }
+}
+
+/// Represents the response to an `EmbeddingRequest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    /// One embedding vector per input, in input order.
+    pub embeddings: Vec<Vec<f32>>,
+    /// Token usage information (if available). `completion_tokens` is always zero.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+/// The asynchronous trait implemented by providers that can compute embeddings.
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Computes embeddings for every input of the request.
+    async fn embed(&self, request: EmbeddingRequest) -> Result<EmbeddingResponse, ProviderError>;
+}
@@ -0,0 +1,196 @@
+//! Typed completions.
+//!
+//! Provides `TypedCompletionExt`, an extension of `LlmProvider` that requests structured
+//! output matching a Rust type's JSON Schema, deserializes the reply into that type and,
+//! when deserialization fails, sends the error back to the model for a bounded number
+//! of repair attempts.
+
+use crate::traits::{
+    ChatMessage, CompletionKind, CompletionRequest, LlmProvider, ProviderError, ResponseFormat,
+};
+use async_trait::async_trait;
+use schemars::JsonSchema as SchemaType;
+use serde::de::DeserializeOwned;
+
+/// Number of repair attempts used by `complete_typed`.
+pub const DEFAULT_REPAIR_ATTEMPTS: u32 = 2;
+
+/// Extension trait adding typed completions to every `LlmProvider`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use merco_llmproxy::{get_provider, ChatMessage, CompletionRequest, LlmConfig, Provider, TypedCompletionExt};
+/// use merco_llmproxy::schemars::JsonSchema;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// #[schemars(crate = "merco_llmproxy::schemars")] // Not needed if you depend on schemars directly
+/// struct City {
+///     name: String,
+///     country: String,
+/// }
+///
+/// # async fn run() -> Result<(), merco_llmproxy::ProviderError> {
+/// let provider = get_provider(LlmConfig::new(Provider::Ollama))?;
+/// let request = CompletionRequest {
+///     model: "qwen3:4b".to_string(),
+///     messages: vec![ChatMessage::user("Which city is the Eiffel Tower in?".to_string())],
+///     ..Default::default()
+/// };
+/// let city: City = provider.complete_typed(request).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait TypedCompletionExt: LlmProvider {
+    /// Requests a completion whose content deserializes into `T`, using
+    /// `DEFAULT_REPAIR_ATTEMPTS` repair attempts.
+    async fn complete_typed<T>(&self, request: CompletionRequest) -> Result<T, ProviderError>
+    where
+        T: DeserializeOwned + SchemaType + Send,
+    {
+        self.complete_typed_with_repairs(request, DEFAULT_REPAIR_ATTEMPTS)
+            .await
+    }
+
+    /// Requests a completion whose content deserializes into `T`.
+    ///
+    /// The request's `response_format` is replaced with the JSON Schema generated for `T`.
+    /// If the reply fails to deserialize, the reply and the parse error are appended to the
+    /// conversation and the model is asked again, up to `max_repair_attempts` times.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProviderError::ParseError` with the last serde error once the repair
+    /// attempts are exhausted, or any error returned by the provider itself.
+    async fn complete_typed_with_repairs<T>(
+        &self,
+        mut request: CompletionRequest,
+        max_repair_attempts: u32,
+    ) -> Result<T, ProviderError>
+    where
+        T: DeserializeOwned + SchemaType + Send,
+    {
+        request.response_format = Some(response_format_for::<T>()?);
+
+        let mut attempt = 0;
+        loop {
+            let response = self.completion(request.clone()).await?;
+            let content = match response.kind() {
+                CompletionKind::Message { content } => content,
+                CompletionKind::ToolCall { .. } => {
+                    return Err(ProviderError::Unexpected(
+                        "Expected a structured message but the model requested tool calls"
+                            .to_string(),
+                    ));
+                }
+            };
+
+            match serde_json::from_str::<T>(strip_code_fence(&content)) {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < max_repair_attempts => {
+                    attempt += 1;
+                    request
+                        .messages
+                        .push(ChatMessage::assistant(Some(content), None));
+                    request.messages.push(ChatMessage::user(format!(
+                        "Your previous response could not be parsed: {}. Respond again with only a JSON value matching the requested schema.",
+                        e
+                    )));
+                }
+                Err(e) => return Err(ProviderError::ParseError(e)),
+            }
+        }
+    }
+}
+
+impl<P: LlmProvider + ?Sized> TypedCompletionExt for P {}
+
+/// Builds a JSON Schema response format for `T`.
+pub fn response_format_for<T: SchemaType>() -> Result<ResponseFormat, ProviderError> {
+    let schema = serde_json::to_value(schemars::schema_for!(T))?;
+    // Providers restrict schema names to letters, digits, '_' and '-'.
+    let name: String = T::schema_name()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Ok(ResponseFormat::JsonSchema {
+        name,
+        schema,
+        strict: false,
+    })
+}
+
+/// Removes a surrounding markdown code fence, which some models add despite instructions.
+fn strip_code_fence(content: &str) -> &str {
+    let trimmed = content.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => {
+            let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest);
+            body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+        }
+        None => trimmed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockProvider;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, SchemaType, PartialEq)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    #[test]
+    fn test_complete_typed_repairs_invalid_reply() {
+        let provider = MockProvider::scripted(&[
+            r#"{"name": "Paris"}"#,
+            "```json\n{\"name\": \"Paris\", \"population\": 2100000}\n```",
+        ]);
+        let request = CompletionRequest {
+            model: "test".to_string(),
+            messages: vec![ChatMessage::user("Describe Paris.".to_string())],
+            ..Default::default()
+        };
+
+        let city: City = futures::executor::block_on(provider.complete_typed(request)).unwrap();
+        assert_eq!(
+            city,
+            City {
+                name: "Paris".to_string(),
+                population: 2_100_000
+            }
+        );
+
+        let requests = provider.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            matches!(&requests[0].response_format, Some(ResponseFormat::JsonSchema { name, .. }) if name == "City")
+        );
+        assert_eq!(requests[1].messages.len(), 3);
+        assert!(requests[1].messages[2]
+            .text()
+            .unwrap()
+            .contains("population"));
+    }
+
+    #[test]
+    fn test_complete_typed_gives_up_after_repairs() {
+        let provider = MockProvider::scripted(&["not json", "still not json"]);
+        let result = futures::executor::block_on(
+            provider.complete_typed_with_repairs::<City>(CompletionRequest::default(), 1),
+        );
+        assert!(matches!(result, Err(ProviderError::ParseError(_))));
+    }
+}